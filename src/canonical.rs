@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use reqwest::{redirect::Policy, Client};
+use url::Url;
+
+use crate::remove_tailing_slash;
+
+/// The max number of redirects followed when resolving a tracking link.
+const MAX_REDIRECTS: usize = 5;
+
+/// Query parameters that only carry tracking information.
+const TRACKING_PARAMS: [&str; 11] = [
+    "fbclid", "gclid", "dclid", "msclkid", "yclid", "igshid", "mc_cid", "mc_eid", "_hsenc",
+    "_hsmi", "ref_src",
+];
+
+/// Normalizes `url` so the same article gets the same key in every channel.
+///
+/// The fragment and tracking query parameters are dropped and the trailing
+/// slash of the path is removed. Urls that cannot be parsed are only trimmed.
+pub fn normalize_url(url: &str) -> String {
+    let url = url.trim();
+    let mut parsed = match Url::parse(url) {
+        Ok(v) => v,
+        Err(_) => return url.to_string(),
+    };
+    parsed.set_fragment(None);
+
+    let pairs = parsed
+        .query_pairs()
+        .filter(|(k, _)| !is_tracking_param(k))
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect::<Vec<_>>();
+    if pairs.is_empty() {
+        parsed.set_query(None);
+    } else {
+        parsed.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+
+    let path = remove_tailing_slash(parsed.path()).to_string();
+    parsed.set_path(&path);
+    if parsed.query().is_none() {
+        remove_tailing_slash(parsed.as_str()).to_string()
+    } else {
+        parsed.to_string()
+    }
+}
+
+/// Builds a client for [`resolve_redirect`], which gives up after
+/// [`MAX_REDIRECTS`] redirects or 10 seconds.
+pub fn redirect_client() -> reqwest::Result<Client> {
+    Client::builder()
+        .redirect(Policy::limited(MAX_REDIRECTS))
+        .timeout(Duration::from_secs(10))
+        .build()
+}
+
+/// Follows the redirects of `url` and returns where they end.
+///
+/// A `HEAD` request is tried first, some sites reject it so `GET` is used
+/// as a fallback.
+pub async fn resolve_redirect(client: &Client, url: &str) -> anyhow::Result<String> {
+    if let Ok(resp) = client.head(url).send().await {
+        if resp.status().is_success() {
+            return Ok(resp.url().to_string());
+        }
+    }
+    let resp = client.get(url).send().await?.error_for_status()?;
+    Ok(resp.url().to_string())
+}
+
+/// Returns the host of `url` without the `www.` prefix.
+pub fn host_of(url: &str) -> Option<String> {
+    let parsed = Url::parse(url.trim()).ok()?;
+    let host = parsed.host_str()?;
+    Some(host.strip_prefix("www.").unwrap_or(host).to_string())
+}
+
+fn is_tracking_param(key: &str) -> bool {
+    key.starts_with("utm_") || TRACKING_PARAMS.contains(&key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_url_should_strip_tracking_params() {
+        assert_eq!(
+            normalize_url(
+                "https://go.dev/blog/chacha8rand?utm_source=golangweekly&utm_medium=email"
+            ),
+            "https://go.dev/blog/chacha8rand"
+        );
+        assert_eq!(
+            normalize_url("https://example.com/post?id=1&fbclid=abc&ref_src=twsrc"),
+            "https://example.com/post?id=1"
+        );
+    }
+
+    #[test]
+    fn normalize_url_should_remove_trailing_slash_and_fragment() {
+        assert_eq!(
+            normalize_url(" https://Redis.io/blog/new-post/#intro "),
+            "https://redis.io/blog/new-post"
+        );
+        assert_eq!(normalize_url("https://go.dev/"), "https://go.dev");
+        assert_eq!(
+            normalize_url("https://example.com/a/?page=2"),
+            "https://example.com/a?page=2"
+        );
+    }
+
+    #[test]
+    fn normalize_url_should_keep_unparsable_input() {
+        assert_eq!(normalize_url(" not a url "), "not a url");
+    }
+
+    #[test]
+    fn host_of_should_work() {
+        assert_eq!(
+            host_of("https://www.go.dev/blog/chacha8rand"),
+            Some("go.dev".to_string())
+        );
+        assert_eq!(
+            host_of("https://github.com/a/b"),
+            Some("github.com".to_string())
+        );
+        assert_eq!(host_of("not a url"), None);
+    }
+}
@@ -0,0 +1,441 @@
+use std::{path::Path, thread, time::Duration};
+
+use tracing::{error, info};
+
+use crate::{
+    conf::AdvisoryConfig,
+    feeds::{Advisory, Range},
+    notifier::{self, notify_all, Color, Message},
+    redis_base::{self, ItemState, Redis},
+    sort_by_date, truncate_str, DEFAULT_ONCE_POST_LIMIT,
+};
+
+/// The max number of characters of the advisory details shown in the card.
+const DETAILS_PREVIEW_LEN: usize = 300;
+
+/// An advisory database in the OSV format.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Database {
+    RustSec,
+    GoVulnDb,
+}
+
+impl Database {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Database::RustSec => "RustSec",
+            Database::GoVulnDb => "Go vulnerability database",
+        }
+    }
+
+    /// The OSV ecosystem of the packages of the database.
+    pub fn ecosystem(&self) -> &'static str {
+        match self {
+            Database::RustSec => "crates.io",
+            Database::GoVulnDb => "Go",
+        }
+    }
+
+    fn redis_key(&self) -> &'static str {
+        match self {
+            Database::RustSec => Redis::HSET_RUSTSEC_KEY,
+            Database::GoVulnDb => Redis::HSET_GO_VULN_KEY,
+        }
+    }
+
+    fn advisory_url(&self, id: &str) -> String {
+        match self {
+            Database::RustSec => format!("https://rustsec.org/advisories/{}.html", id),
+            Database::GoVulnDb => format!("https://pkg.go.dev/vuln/{}", id),
+        }
+    }
+}
+
+pub async fn send_feishu_msg(
+    redis: &redis_base::Redis,
+    db: Database,
+    conf: &AdvisoryConfig,
+    proxy: Option<String>,
+) -> anyhow::Result<()> {
+    info!("start fetching {} advisories", db.name());
+    let advisories = get_advisories(db, conf, proxy).await?;
+    let advisories = filter_advisories(Some(redis), db, conf, advisories);
+    info!(
+        "fetch {} advisories success, advisories: {}",
+        db.name(),
+        advisories.len()
+    );
+    let notifiers = notifier::from_source(redis, db.name(), &conf.source, None);
+    for (advisory, state) in advisories {
+        let msg = build_message(db, conf, &advisory, state);
+        if let Err(e) = notify_all(&notifiers, &msg).await {
+            error!("{}", e);
+        }
+        thread::sleep(Duration::from_secs(3));
+    }
+    info!("finish fetching {} advisories", db.name());
+    Ok(())
+}
+
+/// Reads the advisories of the local checkout if configured, or queries
+/// `api.osv.dev` for each watched package.
+async fn get_advisories(
+    db: Database,
+    conf: &AdvisoryConfig,
+    proxy: Option<String>,
+) -> anyhow::Result<Vec<Advisory>> {
+    if let Some(path) = &conf.local_path {
+        return Advisory::read_dir(Path::new(path));
+    }
+    let mut advisories: Vec<Advisory> = vec![];
+    for package in &conf.packages {
+        match Advisory::query(db.ecosystem(), package, proxy.clone()).await {
+            Ok(v) => {
+                // An advisory can affect several watched packages.
+                for advisory in v {
+                    if advisories.iter().all(|a| a.id != advisory.id) {
+                        advisories.push(advisory);
+                    }
+                }
+            }
+            Err(e) => error!("fetch advisories of {} failed: {}", package, e),
+        }
+    }
+    Ok(advisories)
+}
+
+fn filter_advisories(
+    redis: Option<&redis_base::Redis>,
+    db: Database,
+    conf: &AdvisoryConfig,
+    mut advisories: Vec<Advisory>,
+) -> Vec<(Advisory, ItemState)> {
+    let mut once_post_limit = conf.source.once_post_limit;
+    if once_post_limit == 0 {
+        once_post_limit = DEFAULT_ONCE_POST_LIMIT
+    }
+    sort_by_date(&mut advisories, conf.source.order, |v| &v.published);
+
+    advisories
+        .into_iter()
+        .filter(|v| v.withdrawn.is_none())
+        .filter(|v| conf.packages.iter().any(|p| v.affects(db.ecosystem(), p)))
+        .filter_map(|v| {
+            let state = match redis {
+                Some(r) => r.observe(db.redis_key(), &v.id, &v.modified, ""),
+                None => ItemState::New,
+            };
+            match state {
+                ItemState::New => Some((v, state)),
+                ItemState::Updated if conf.source.notify_updates => Some((v, state)),
+                _ => None,
+            }
+        })
+        .take(once_post_limit as usize)
+        .collect()
+}
+
+/// Formats the affected versions of a range, e.g. `>=1.19.0, <1.20.3`.
+fn affected_versions(range: &Range) -> Vec<String> {
+    let mut res = vec![];
+    let mut introduced: Option<&str> = None;
+    for event in &range.events {
+        if let Some(v) = &event.introduced {
+            introduced = Some(v.as_str()).filter(|v| *v != "0" && *v != "0.0.0-0");
+            continue;
+        }
+        let upper = match (&event.fixed, &event.last_affected) {
+            (Some(v), _) => format!("<{}", v),
+            (None, Some(v)) => format!("<={}", v),
+            (None, None) => continue,
+        };
+        match introduced.take() {
+            Some(lower) => res.push(format!(">={}, {}", lower, upper)),
+            None => res.push(upper),
+        }
+    }
+    if let Some(lower) = introduced {
+        res.push(format!(">={}", lower));
+    } else if res.is_empty() {
+        res.push("all versions".to_string());
+    }
+    res
+}
+
+fn patched_versions(range: &Range) -> Vec<String> {
+    range
+        .events
+        .iter()
+        .filter_map(|v| v.fixed.as_ref().map(|v| format!(">={}", v)))
+        .collect()
+}
+
+/// Returns the severity rating and the CVSS v3 base score of the advisory.
+fn severity(advisory: &Advisory) -> Option<(&'static str, f64)> {
+    let score = advisory
+        .severity
+        .iter()
+        .filter(|v| v.kind == "CVSS_V3")
+        .find_map(|v| cvss3_base_score(&v.score))?;
+    let rating = match score {
+        s if s <= 0.0 => "None",
+        s if s < 4.0 => "Low",
+        s if s < 7.0 => "Medium",
+        s if s < 9.0 => "High",
+        _ => "Critical",
+    };
+    Some((rating, score))
+}
+
+/// Computes the base score of a CVSS v3 vector, see
+/// <https://www.first.org/cvss/v3.1/specification-document#7-1-Base-Metrics-Equations>.
+fn cvss3_base_score(vector: &str) -> Option<f64> {
+    let metric = |name: &str| {
+        vector
+            .split('/')
+            .find_map(|v| v.strip_prefix(name)?.strip_prefix(':'))
+    };
+    let changed = match metric("S")? {
+        "U" => false,
+        "C" => true,
+        _ => return None,
+    };
+    let av = match metric("AV")? {
+        "N" => 0.85,
+        "A" => 0.62,
+        "L" => 0.55,
+        "P" => 0.2,
+        _ => return None,
+    };
+    let ac = match metric("AC")? {
+        "L" => 0.77,
+        "H" => 0.44,
+        _ => return None,
+    };
+    let pr = match (metric("PR")?, changed) {
+        ("N", _) => 0.85,
+        ("L", false) => 0.62,
+        ("L", true) => 0.68,
+        ("H", false) => 0.27,
+        ("H", true) => 0.5,
+        _ => return None,
+    };
+    let ui = match metric("UI")? {
+        "N" => 0.85,
+        "R" => 0.62,
+        _ => return None,
+    };
+    let cia = |name: &str| match metric(name)? {
+        "H" => Some(0.56),
+        "L" => Some(0.22),
+        "N" => Some(0.0),
+        _ => None,
+    };
+    let iss: f64 = 1.0 - (1.0 - cia("C")?) * (1.0 - cia("I")?) * (1.0 - cia("A")?);
+    let impact = if changed {
+        7.52 * (iss - 0.029) - 3.25 * (iss - 0.02).powi(15)
+    } else {
+        6.42 * iss
+    };
+    if impact <= 0.0 {
+        return Some(0.0);
+    }
+    let exploitability = 8.22 * av * ac * pr * ui;
+    let score = if changed {
+        1.08 * (impact + exploitability)
+    } else {
+        impact + exploitability
+    };
+    Some(round_up(score.min(10.0)))
+}
+
+/// The `Roundup` of the specification, which avoids floating point errors.
+fn round_up(v: f64) -> f64 {
+    let int = (v * 100_000.0).round() as i64;
+    if int % 10_000 == 0 {
+        int as f64 / 100_000.0
+    } else {
+        (int / 10_000 + 1) as f64 / 10.0
+    }
+}
+
+fn build_content(db: Database, conf: &AdvisoryConfig, advisory: &Advisory) -> String {
+    let mut content = String::with_capacity(1024);
+    match severity(advisory) {
+        Some((rating, score)) => {
+            content.push_str(&format!("**Severity**: {} ({:.1})\n", rating, score))
+        }
+        None => content.push_str("**Severity**: unknown\n"),
+    }
+    if !advisory.aliases.is_empty() {
+        content.push_str(&format!("**Aliases**: {}\n", advisory.aliases.join(", ")));
+    }
+    for affected in &advisory.affected {
+        if affected.package.ecosystem != db.ecosystem()
+            || !conf.packages.contains(&affected.package.name)
+        {
+            continue;
+        }
+        let ranges = affected.ranges.iter().filter(|v| v.kind != "GIT");
+        let affected_versions = ranges
+            .clone()
+            .flat_map(affected_versions)
+            .collect::<Vec<_>>();
+        let patched = ranges.flat_map(patched_versions).collect::<Vec<_>>();
+        content.push_str(&format!(
+            "**Package**: {}\n**Affected versions**: {}\n**Patched versions**: {}\n",
+            affected.package.name,
+            affected_versions.join("; "),
+            if patched.is_empty() {
+                "none".to_string()
+            } else {
+                patched.join(", ")
+            },
+        ));
+    }
+    if !advisory.details.is_empty() {
+        content.push('\n');
+        content.push_str(&truncate_str(advisory.details.trim(), DETAILS_PREVIEW_LEN));
+    }
+    content
+}
+
+fn build_message(
+    db: Database,
+    conf: &AdvisoryConfig,
+    advisory: &Advisory,
+    state: ItemState,
+) -> Message {
+    let mut title = format!("[{}] {}", advisory.id, advisory.summary);
+    if state == ItemState::Updated {
+        title = format!("[Updated] {}", title);
+    }
+    let color = match severity(advisory) {
+        Some(("Critical", _)) | Some(("High", _)) => Color::Red,
+        Some(("Medium", _)) => Color::Orange,
+        _ => Color::Yellow,
+    };
+    Message::new(title, color)
+        .markdown(build_content(db, conf, advisory))
+        .button("advisory", db.advisory_url(&advisory.id))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        conf::ArticleSourceConfig,
+        notifier::{Button, Element},
+    };
+
+    use super::*;
+
+    fn rustsec() -> Advisory {
+        Advisory::parse(include_str!("../../fixtures/osv_rustsec.json")).unwrap()
+    }
+
+    fn go_advisories() -> Vec<Advisory> {
+        let data = include_str!("../../fixtures/osv_go_query.json");
+        let value: serde_json::Value = serde_json::from_str(data).unwrap();
+        serde_json::from_value(value["vulns"].clone()).unwrap()
+    }
+
+    fn conf(packages: &[&str]) -> AdvisoryConfig {
+        AdvisoryConfig {
+            packages: packages.iter().map(|v| v.to_string()).collect(),
+            local_path: None,
+            source: ArticleSourceConfig::default(),
+        }
+    }
+
+    #[test]
+    fn test_cvss3_base_score() {
+        let score = |v| cvss3_base_score(v).unwrap();
+        assert_eq!(score("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:N/I:N/A:H"), 7.5);
+        assert_eq!(score("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H"), 9.8);
+        assert_eq!(score("CVSS:3.1/AV:N/AC:L/PR:N/UI:R/S:C/C:L/I:L/A:N"), 6.1);
+        assert_eq!(score("CVSS:3.1/AV:L/AC:H/PR:H/UI:R/S:U/C:N/I:N/A:N"), 0.0);
+        assert_eq!(cvss3_base_score("CVSS:4.0/AV:N/AC:L/AT:N"), None);
+    }
+
+    #[test]
+    fn test_affected_versions() {
+        let advisory = rustsec();
+        let range = &advisory.affected[0].ranges[0];
+        assert_eq!(
+            affected_versions(range),
+            vec!["<1.18.4", ">=1.19.0, <1.20.3", ">=1.21.0, <1.23.1"]
+        );
+        assert_eq!(
+            patched_versions(range),
+            vec![">=1.18.4", ">=1.20.3", ">=1.23.1"]
+        );
+    }
+
+    #[test]
+    fn test_filter_advisories() {
+        let res = filter_advisories(
+            None,
+            Database::GoVulnDb,
+            &conf(&["golang.org/x/net"]),
+            go_advisories(),
+        );
+        assert_eq!(res.len(), 1, "the withdrawn advisory is skipped");
+        assert_eq!(res[0].0.id, "GO-2024-2687");
+
+        let res = filter_advisories(
+            None,
+            Database::GoVulnDb,
+            &conf(&["stdlib"]),
+            go_advisories(),
+        );
+        assert!(res.is_empty());
+
+        let res = filter_advisories(None, Database::GoVulnDb, &conf(&["tokio"]), vec![rustsec()]);
+        assert!(res.is_empty(), "the ecosystem must match");
+    }
+
+    #[test]
+    fn test_build_message() {
+        let msg = build_message(
+            Database::RustSec,
+            &conf(&["tokio"]),
+            &rustsec(),
+            ItemState::New,
+        );
+        assert_eq!(
+            msg.title,
+            "[RUSTSEC-2023-0005] `tokio::io::ReadHalf<T>::unsplit` is Unsound"
+        );
+        assert_eq!(msg.color, Color::Red);
+        let Element::Markdown(content) = &msg.elements[0] else {
+            panic!("the message should start with markdown");
+        };
+        assert!(content.starts_with(
+            "**Severity**: High (7.5)\n\
+             **Aliases**: GHSA-4q83-7cq4-p6wg\n\
+             **Package**: tokio\n\
+             **Affected versions**: <1.18.4; >=1.19.0, <1.20.3; >=1.21.0, <1.23.1\n\
+             **Patched versions**: >=1.18.4, >=1.20.3, >=1.23.1\n"
+        ));
+        assert_eq!(
+            msg.elements[1],
+            Element::Buttons(vec![Button {
+                text: "advisory".to_string(),
+                url: "https://rustsec.org/advisories/RUSTSEC-2023-0005.html".to_string(),
+            }])
+        );
+
+        let go = &go_advisories()[0];
+        let msg = build_message(
+            Database::GoVulnDb,
+            &conf(&["golang.org/x/net"]),
+            go,
+            ItemState::Updated,
+        );
+        assert_eq!(
+            msg.title,
+            "[Updated] [GO-2024-2687] HTTP/2 CONTINUATION flood in net/http"
+        );
+        assert_eq!(msg.color, Color::Yellow);
+    }
+}
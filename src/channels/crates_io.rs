@@ -0,0 +1,230 @@
+use std::{thread, time::Duration};
+
+use tracing::{error, info};
+
+use crate::{
+    conf::{CratesIoConfig, PostOrder, WatchedCrateConfig},
+    feeds::{CrateInfo, CrateVersion},
+    notifier::{self, notify_all, Color, Message},
+    redis_base::{self, ItemState, Redis},
+    sort_by_date, DEFAULT_ONCE_POST_LIMIT,
+};
+
+pub async fn send_feishu_msg(
+    redis: &redis_base::Redis,
+    conf: &CratesIoConfig,
+    proxy: Option<String>,
+) -> anyhow::Result<()> {
+    info!("start fetching crates.io versions");
+    let notifiers = notifier::from_source(redis, "crates.io", &conf.source, None);
+    for krate in &conf.crates {
+        // One unknown crate must not stop the others.
+        let info = match CrateInfo::try_new(&krate.name, proxy.clone()).await {
+            Ok(v) => v,
+            Err(e) => {
+                error!("fetch crates.io versions of {} failed: {}", krate.name, e);
+                continue;
+            }
+        };
+        let versions = filter_versions(Some(redis), conf, &info);
+        info!(
+            "fetch crates.io versions of {} success, versions: {}",
+            krate.name,
+            versions.len()
+        );
+        for (version, state) in versions {
+            thread::sleep(Duration::from_secs(3));
+            let msg = build_message(krate, &info, version, state);
+            if let Err(e) = notify_all(&notifiers, &msg).await {
+                error!("{}", e);
+            }
+        }
+    }
+    info!("finish fetching crates.io versions");
+    Ok(())
+}
+
+/// Returns the new versions of the crate and the ones whose yank status
+/// changed since the last poll.
+///
+/// Only the `once_post_limit` latest versions are looked at, so the first
+/// poll of a crate does not replay its whole history.
+fn filter_versions<'a>(
+    redis: Option<&redis_base::Redis>,
+    conf: &CratesIoConfig,
+    info: &'a CrateInfo,
+) -> Vec<(&'a CrateVersion, ItemState)> {
+    let mut once_post_limit = conf.source.once_post_limit;
+    if once_post_limit == 0 {
+        once_post_limit = DEFAULT_ONCE_POST_LIMIT
+    }
+    let mut versions = info
+        .versions
+        .iter()
+        .filter(|v| conf.include_prereleases || !v.num.contains('-'))
+        .collect::<Vec<_>>();
+    sort_by_date(&mut versions, PostOrder::NewestFirst, |v| &v.created_at);
+    versions.truncate(once_post_limit as usize);
+    sort_by_date(&mut versions, conf.source.order, |v| &v.created_at);
+
+    versions
+        .into_iter()
+        .filter_map(|v| {
+            let state = match redis {
+                Some(r) => r.observe(
+                    Redis::HSET_CRATES_IO_KEY,
+                    &format!("{}@{}", v.krate, v.num),
+                    "",
+                    &v.yanked.to_string(),
+                ),
+                None => ItemState::New,
+            };
+            match state {
+                ItemState::Unchanged => None,
+                _ => Some((v, state)),
+            }
+        })
+        .collect()
+}
+
+fn version_title(version: &CrateVersion, state: ItemState) -> String {
+    let title = format!("{} {}", version.krate, version.num);
+    match state {
+        ItemState::Updated if version.yanked => format!("[Yanked] {}", title),
+        ItemState::Updated => format!("[Unyanked] {}", title),
+        _ if version.yanked => format!("{} (yanked)", title),
+        _ => title,
+    }
+}
+
+fn build_content(info: &CrateInfo, version: &CrateVersion) -> String {
+    let mut content = String::with_capacity(512);
+    if let Some(desc) = &info.krate.description {
+        content.push_str(desc.trim());
+        content.push_str("\n\n");
+    }
+    content.push_str(&format!("**Published**: {}", version.created_at));
+    if let Some(by) = &version.published_by {
+        content.push_str(&format!(" by {}", by.login));
+    }
+    content.push('\n');
+    if let Some(license) = &version.license {
+        content.push_str(&format!("**License**: {}\n", license));
+    }
+    content.push_str(&format!(
+        "**Yanked**: {}\n",
+        if version.yanked { "yes" } else { "no" }
+    ));
+    if let Some(max) = &info.krate.max_stable_version {
+        if max != &version.num {
+            content.push_str(&format!("**Latest stable**: {}\n", max));
+        }
+    }
+    content
+}
+
+fn build_message(
+    krate: &WatchedCrateConfig,
+    info: &CrateInfo,
+    version: &CrateVersion,
+    state: ItemState,
+) -> Message {
+    let color = if version.yanked {
+        Color::Red
+    } else {
+        Color::Wathet
+    };
+    let mut msg = Message::new(version_title(version, state), color)
+        .markdown(build_content(info, version))
+        .button(
+            "crates.io",
+            format!("https://crates.io/crates/{}/{}", version.krate, version.num),
+        );
+    match (&krate.changelog, &info.krate.repository) {
+        (Some(url), _) => msg = msg.button("changelog", url.as_str()),
+        (None, Some(url)) => msg = msg.button("repository", url.as_str()),
+        (None, None) => {}
+    }
+    msg
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{conf::ArticleSourceConfig, notifier::Element};
+
+    use super::*;
+
+    fn info() -> CrateInfo {
+        CrateInfo::parse(include_str!("../../fixtures/crates_io_tokio.json")).unwrap()
+    }
+
+    fn conf(once_post_limit: u8) -> CratesIoConfig {
+        CratesIoConfig {
+            include_prereleases: false,
+            crates: vec![],
+            source: ArticleSourceConfig {
+                once_post_limit,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_filter_versions() {
+        let info = info();
+        let versions = filter_versions(None, &conf(3), &info);
+        let nums = versions
+            .iter()
+            .map(|(v, _)| v.num.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(nums, vec!["1.38.1", "1.39.0", "1.39.1"]);
+    }
+
+    #[test]
+    fn test_version_title() {
+        let info = info();
+        assert_eq!(
+            version_title(&info.versions[0], ItemState::New),
+            "tokio 1.39.1"
+        );
+        assert_eq!(
+            version_title(&info.versions[1], ItemState::New),
+            "tokio 1.39.0 (yanked)"
+        );
+        assert_eq!(
+            version_title(&info.versions[1], ItemState::Updated),
+            "[Yanked] tokio 1.39.0"
+        );
+        assert_eq!(
+            version_title(&info.versions[0], ItemState::Updated),
+            "[Unyanked] tokio 1.39.1"
+        );
+    }
+
+    #[test]
+    fn test_build_message() {
+        let info = info();
+        let krate = WatchedCrateConfig {
+            name: "tokio".to_string(),
+            changelog: None,
+        };
+        let msg = build_message(&krate, &info, &info.versions[2], ItemState::New);
+        assert_eq!(
+            msg.elements[0],
+            Element::Markdown(
+            "An event-driven, non-blocking I/O platform for writing asynchronous I/O backed applications.\n\n\
+             **Published**: 2024-07-16T09:04:38.470226+00:00\n\
+             **License**: MIT\n\
+             **Yanked**: no\n\
+             **Latest stable**: 1.39.1\n"
+                    .to_string()
+            )
+        );
+        let Element::Buttons(buttons) = &msg.elements[1] else {
+            panic!("the message should end with buttons");
+        };
+        assert_eq!(buttons[0].url, "https://crates.io/crates/tokio/1.38.1");
+        assert_eq!(buttons[1].text, "repository");
+        assert_eq!(buttons[1].url, "https://github.com/tokio-rs/tokio");
+    }
+}
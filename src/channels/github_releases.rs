@@ -0,0 +1,356 @@
+use std::{sync::LazyLock, thread, time::Duration};
+
+use regex::Regex;
+use scraper::Html;
+use semver::{Version, VersionReq};
+use tracing::{error, info};
+
+use crate::{
+    chatgpt::build_feishu_content,
+    conf::{GithubReleasesConfig, GithubRepoConfig},
+    feeds::{Atom, Entry},
+    notifier::{self, notify_all, Color, Message},
+    redis_base::{self, Redis},
+    sort_by_date, trim_str, truncate_str, DEFAULT_ONCE_POST_LIMIT,
+};
+
+/// The max number of characters of the release notes shown in the card.
+const NOTES_PREVIEW_LEN: usize = 300;
+
+#[derive(Debug)]
+pub struct Release {
+    pub repo: String,
+    pub tag: String,
+    pub version: Option<Version>,
+    pub prerelease: bool,
+    pub entry: Entry,
+}
+
+pub async fn send_feishu_msg(
+    redis: &redis_base::Redis,
+    conf: &GithubReleasesConfig,
+    openai_api_key: Option<String>,
+    openai_host: Option<String>,
+    proxy: Option<String>,
+) -> anyhow::Result<()> {
+    info!("start fetching github releases");
+    let notifiers = notifier::from_source(redis, "GitHub Releases", &conf.source, None);
+    for repo in &conf.repos {
+        let url = format!("https://github.com/{}/releases.atom", repo.repo);
+        // One broken repository must not stop the others.
+        let atom = match Atom::try_new(&url, proxy.clone()).await {
+            Ok(v) => v,
+            Err(e) => {
+                error!("fetch github releases of {} failed: {}", repo.repo, e);
+                continue;
+            }
+        };
+        let releases = filter_releases(Some(redis), conf, repo, atom.entry);
+        info!(
+            "fetch github releases of {} success, releases: {}",
+            repo.repo,
+            releases.len()
+        );
+        for release in releases {
+            thread::sleep(Duration::from_secs(3));
+            let content = build_content(
+                &release,
+                openai_api_key.clone(),
+                openai_host.clone(),
+                proxy.clone(),
+            )
+            .await;
+            let color = if release.prerelease {
+                Color::Yellow
+            } else {
+                Color::Purple
+            };
+            let msg = Message::new(release_title(&release), color)
+                .date(release.entry.updated.as_str())
+                .markdown(content)
+                .button("release notes", release.entry.link.href.as_str());
+            if let Err(e) = notify_all(&notifiers, &msg).await {
+                error!("{}", e);
+            }
+        }
+    }
+    info!("finish fetching github releases");
+    Ok(())
+}
+
+/// Keeps the unsent releases of `repo` that pass its filters, at most
+/// `once_post_limit` of them.
+fn filter_releases(
+    redis: Option<&redis_base::Redis>,
+    conf: &GithubReleasesConfig,
+    repo: &GithubRepoConfig,
+    mut entries: Vec<Entry>,
+) -> Vec<Release> {
+    let mut once_post_limit = conf.source.once_post_limit;
+    if once_post_limit == 0 {
+        once_post_limit = DEFAULT_ONCE_POST_LIMIT
+    }
+    let include_prereleases = repo.include_prereleases.unwrap_or(conf.include_prereleases);
+    let req = match repo.version.as_deref().map(VersionReq::parse).transpose() {
+        Ok(v) => v,
+        Err(e) => {
+            error!("invalid version requirement of {}: {}", repo.repo, e);
+            return vec![];
+        }
+    };
+    sort_by_date(&mut entries, conf.source.order, |v| v.date());
+
+    entries
+        .into_iter()
+        .filter_map(|entry| resolve_release(&repo.repo, repo.tag_prefix.as_deref(), entry))
+        .filter(|v| include_prereleases || !v.prerelease)
+        .filter(|v| match (&req, &v.version) {
+            (None, _) => true,
+            (Some(req), Some(version)) => req.matches(version) || matches_prerelease(req, version),
+            (Some(_), None) => false,
+        })
+        .filter(|v| match redis {
+            Some(r) => r.setnx(Redis::HSET_GITHUB_RELEASES_KEY, &v.entry.id),
+            None => true,
+        })
+        .take(once_post_limit as usize)
+        .collect()
+}
+
+/// Builds a release from a feed entry, its tag is the last segment of the
+/// link, e.g. `.../releases/tag/tokio-1.39.0`.
+///
+/// With a `tag_prefix` only the tags starting with it are kept, the rest of
+/// the tag must then be a version, so `tokio-` does not match `tokio-util-0.7.11`.
+fn resolve_release(repo: &str, tag_prefix: Option<&str>, entry: Entry) -> Option<Release> {
+    let tag = entry.link.href.rsplit('/').next()?.to_string();
+    let version = match tag_prefix {
+        Some(prefix) => Some(parse_version(tag.strip_prefix(prefix)?)?),
+        None => tag
+            .find(|c: char| c.is_ascii_digit())
+            .and_then(|i| parse_version(&tag[i..])),
+    };
+    let prerelease = match &version {
+        Some(v) => !v.pre.is_empty(),
+        None => is_prerelease_tag(&tag),
+    };
+    Some(Release {
+        repo: repo.to_string(),
+        tag,
+        version,
+        prerelease,
+        entry,
+    })
+}
+
+// Go style pre-releases, e.g. `go1.23rc1` and `go1.23beta1`.
+static GO_PRERELEASE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\d(rc|beta|alpha)\d+$").unwrap());
+
+/// Whether a tag that is not semver names a pre-release.
+fn is_prerelease_tag(tag: &str) -> bool {
+    let tag = tag.to_lowercase();
+    ["-rc", "-beta", "-alpha", "-pre"]
+        .iter()
+        .any(|v| tag.contains(v))
+        || GO_PRERELEASE_RE.is_match(&tag)
+}
+
+/// Parses a version leniently, `v1.22` is read as `1.22.0`.
+fn parse_version(s: &str) -> Option<Version> {
+    let s = s.trim().trim_start_matches('v');
+    if let Ok(v) = Version::parse(s) {
+        return Some(v);
+    }
+    let (core, pre) = match s.split_once('-') {
+        Some((core, pre)) => (core, Some(pre)),
+        None => (s, None),
+    };
+    let mut parts = core.split('.').collect::<Vec<_>>();
+    if parts.is_empty() || parts.len() > 3 {
+        return None;
+    }
+    while parts.len() < 3 {
+        parts.push("0");
+    }
+    let mut v = parts.join(".");
+    if let Some(pre) = pre {
+        v.push('-');
+        v.push_str(pre);
+    }
+    Version::parse(&v).ok()
+}
+
+/// `VersionReq` never matches a pre-release of another version, compare
+/// its release version instead so `>=1.38` matches `1.39.0-rc.1`.
+fn matches_prerelease(req: &VersionReq, version: &Version) -> bool {
+    if version.pre.is_empty() {
+        return false;
+    }
+    let mut release = version.clone();
+    release.pre = semver::Prerelease::EMPTY;
+    req.matches(&release)
+}
+
+fn release_title(release: &Release) -> String {
+    let mut title = format!("{} {}", release.repo, release.tag);
+    if release.prerelease {
+        title.push_str(" (pre-release)");
+    }
+    title
+}
+
+fn build_req_content(release: &Release, notes: &str) -> String {
+    let mut res = String::with_capacity(notes.len() + 128);
+    res.push_str(&format!(
+        "这是 {} 的 {} 版本的发布说明：\n",
+        release.repo, release.tag
+    ));
+    res.push_str(notes);
+    res.push('\n');
+    res.push_str(
+        "请你使用中文总结这个版本的主要变化，特别是不兼容的变更和安全修复，不要超过150个字。\n",
+    );
+    res
+}
+
+async fn build_content(
+    release: &Release,
+    openai_api_key: Option<String>,
+    openai_host: Option<String>,
+    proxy: Option<String>,
+) -> String {
+    let notes = trim_str(
+        &Html::parse_fragment(&release.entry.content)
+            .root_element()
+            .text()
+            .collect::<Vec<_>>()
+            .join(" "),
+    );
+    let mut content = String::with_capacity(4096);
+    content.push_str(&truncate_str(&notes, NOTES_PREVIEW_LEN));
+    content.push_str(
+        &build_feishu_content(
+            openai_api_key,
+            openai_host,
+            proxy,
+            build_req_content(release, &notes),
+        )
+        .await,
+    );
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::conf::ArticleSourceConfig;
+
+    use super::*;
+
+    fn entries() -> Vec<Entry> {
+        let data = include_str!("../../fixtures/github_releases.xml");
+        Atom::parse(data).unwrap().entry
+    }
+
+    fn conf(include_prereleases: bool) -> GithubReleasesConfig {
+        GithubReleasesConfig {
+            include_prereleases,
+            repos: vec![],
+            source: ArticleSourceConfig::default(),
+        }
+    }
+
+    fn repo(version: Option<&str>, tag_prefix: Option<&str>) -> GithubRepoConfig {
+        GithubRepoConfig {
+            repo: "tokio-rs/tokio".to_string(),
+            version: version.map(|v| v.to_string()),
+            tag_prefix: tag_prefix.map(|v| v.to_string()),
+            include_prereleases: None,
+        }
+    }
+
+    fn tags(releases: &[Release]) -> Vec<&str> {
+        releases.iter().map(|v| v.tag.as_str()).collect()
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("v1.22"), Some(Version::new(1, 22, 0)));
+        assert_eq!(parse_version("1.39.0"), Some(Version::new(1, 39, 0)));
+        assert_eq!(
+            parse_version("1.23rc1"),
+            None,
+            "go style pre-releases are not semver"
+        );
+        assert_eq!(
+            parse_version("1.39-rc.1").map(|v| v.pre.to_string()),
+            Some("rc.1".to_string())
+        );
+        assert_eq!(parse_version("util-0.7.11"), None);
+    }
+
+    #[test]
+    fn test_filter_releases() {
+        let releases = filter_releases(None, &conf(false), &repo(None, None), entries());
+        assert_eq!(
+            tags(&releases),
+            vec!["tokio-1.38.1", "tokio-1.39.0", "tokio-util-0.7.11"]
+        );
+
+        let releases = filter_releases(None, &conf(true), &repo(None, Some("tokio-")), entries());
+        assert_eq!(
+            tags(&releases),
+            vec!["tokio-1.38.1", "tokio-1.39.0-rc.1", "tokio-1.39.0"]
+        );
+        assert!(releases[1].prerelease);
+    }
+
+    #[test]
+    fn test_filter_releases_with_version_requirement() {
+        let releases = filter_releases(
+            None,
+            &conf(true),
+            &repo(Some(">=1.39"), Some("tokio-")),
+            entries(),
+        );
+        assert_eq!(tags(&releases), vec!["tokio-1.39.0-rc.1", "tokio-1.39.0"]);
+
+        let releases = filter_releases(
+            None,
+            &conf(false),
+            &repo(Some("not a req"), None),
+            entries(),
+        );
+        assert!(releases.is_empty());
+    }
+
+    #[test]
+    fn test_filter_go_releases() {
+        let data = include_str!("../../fixtures/github_releases_go.xml");
+        let entries = || Atom::parse(data).unwrap().entry;
+        let repo = GithubRepoConfig {
+            repo: "golang/go".to_string(),
+            ..repo(None, None)
+        };
+        let releases = filter_releases(None, &conf(false), &repo, entries());
+        assert_eq!(tags(&releases), vec!["go1.22.5"]);
+
+        let releases = filter_releases(None, &conf(true), &repo, entries());
+        assert_eq!(
+            tags(&releases),
+            vec!["go1.23beta1", "go1.23rc1", "go1.22.5"]
+        );
+        assert!(releases[0].prerelease && releases[1].prerelease);
+        assert!(is_prerelease_tag("go1.21rc2"));
+        assert!(!is_prerelease_tag("go1.21.12"));
+    }
+
+    #[test]
+    fn test_release_title() {
+        let releases = filter_releases(None, &conf(true), &repo(None, Some("tokio-")), entries());
+        assert_eq!(
+            release_title(&releases[1]),
+            "tokio-rs/tokio tokio-1.39.0-rc.1 (pre-release)"
+        );
+    }
+}
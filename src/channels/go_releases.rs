@@ -0,0 +1,309 @@
+use std::{collections::HashMap, thread, time::Duration};
+
+use regex::Regex;
+use reqwest::{Client, Proxy};
+use scraper::{Html, Selector};
+use semver::Version;
+use serde::Deserialize;
+use tracing::{error, info};
+
+use crate::{
+    conf::{ArticleSourceConfig, PostOrder},
+    notifier::{self, notify_all, Color, Message},
+    redis_base::{self, Redis},
+    trim_str, DEFAULT_ONCE_POST_LIMIT,
+};
+
+const GO_DL_JSON_URL: &str = "https://go.dev/dl/?mode=json";
+const GO_RELEASE_HISTORY_URL: &str = "https://go.dev/doc/devel/release";
+
+/// A Go version listed by `go.dev/dl`.
+#[derive(Debug, Deserialize)]
+pub struct GoDownload {
+    pub version: String,
+    pub stable: bool,
+}
+
+/// What the release history says about a version.
+#[derive(Debug, Default, PartialEq)]
+pub struct ReleaseNote {
+    pub date: String,
+    pub summary: String,
+    pub security: bool,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct GoRelease {
+    pub version: String,
+    pub note: ReleaseNote,
+}
+
+impl GoRelease {
+    pub fn is_security(&self) -> bool {
+        self.note.security
+    }
+
+    /// Major releases have their own release notes, the minor ones only
+    /// have an entry in the release history.
+    pub fn notes_url(&self) -> String {
+        match self.version.strip_suffix(".0") {
+            Some(major) if major.matches('.').count() == 1 => {
+                format!("https://go.dev/doc/{}", major)
+            }
+            _ => format!("{}#{}", GO_RELEASE_HISTORY_URL, self.version),
+        }
+    }
+}
+
+pub async fn send_feishu_msg(
+    redis: &redis_base::Redis,
+    source: &ArticleSourceConfig,
+    proxy: Option<String>,
+) -> anyhow::Result<()> {
+    info!("start fetching go releases");
+    let releases = get_releases(Some(redis), source, proxy).await?;
+    info!("fetch go releases success, releases: {}", releases.len());
+    let notifiers = notifier::from_source(redis, "Go Releases", source, None);
+    for release in releases {
+        thread::sleep(Duration::from_secs(3));
+        let msg = build_message(&release);
+        if let Err(e) = notify_all(&notifiers, &msg).await {
+            error!("{}", e);
+        }
+    }
+    info!("finish fetching go releases");
+    Ok(())
+}
+
+async fn get_releases(
+    redis: Option<&redis_base::Redis>,
+    source: &ArticleSourceConfig,
+    proxy: Option<String>,
+) -> anyhow::Result<Vec<GoRelease>> {
+    let downloads: Vec<GoDownload> =
+        serde_json::from_str(&send_request(GO_DL_JSON_URL, proxy.clone()).await?)?;
+    let notes = parse_release_history(&send_request(GO_RELEASE_HISTORY_URL, proxy).await?);
+    Ok(filter_releases(redis, source, downloads, notes))
+}
+
+fn filter_releases(
+    redis: Option<&redis_base::Redis>,
+    source: &ArticleSourceConfig,
+    downloads: Vec<GoDownload>,
+    mut notes: HashMap<String, ReleaseNote>,
+) -> Vec<GoRelease> {
+    let mut once_post_limit = source.once_post_limit;
+    if once_post_limit == 0 {
+        once_post_limit = DEFAULT_ONCE_POST_LIMIT
+    }
+    let mut downloads = downloads
+        .into_iter()
+        .filter(|v| v.stable)
+        .collect::<Vec<_>>();
+    // The listing has no dates, versions are ordered instead.
+    match source.order {
+        PostOrder::OldestFirst => downloads.sort_by_key(|v| parse_version(&v.version)),
+        PostOrder::NewestFirst => {
+            downloads.sort_by_key(|v| std::cmp::Reverse(parse_version(&v.version)))
+        }
+        PostOrder::FeedOrder => {}
+    }
+
+    // The security flag comes from the release history, which often lags the
+    // download listing. A release is held until its entry shows up, it is not
+    // marked as sent before.
+    downloads
+        .into_iter()
+        .filter_map(|v| match notes.remove(&v.version) {
+            Some(note) => Some((v, note)),
+            None => {
+                info!("hold {}, it has no release history entry yet", v.version);
+                None
+            }
+        })
+        .filter(|(v, _)| match redis {
+            Some(r) => r.setnx(Redis::HSET_GO_RELEASES_KEY, &v.version),
+            None => true,
+        })
+        .take(once_post_limit as usize)
+        .map(|(v, note)| GoRelease {
+            version: v.version,
+            note,
+        })
+        .collect()
+}
+
+/// Reads `go1.22.5` as `1.22.5`, older major versions are named `go1.20`.
+fn parse_version(version: &str) -> Option<Version> {
+    let v = version.strip_prefix("go")?;
+    match v.matches('.').count() {
+        1 => Version::parse(&format!("{}.0", v)).ok(),
+        _ => Version::parse(v).ok(),
+    }
+}
+
+/// Extracts the paragraphs of `go.dev/doc/devel/release` by version.
+pub fn parse_release_history(html: &str) -> HashMap<String, ReleaseNote> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("h2[id^=go], p[id^=go]").unwrap();
+    let paragraph = Selector::parse("p").unwrap();
+    let released = Regex::new(r"\(released (\d{4}-\d{2}-\d{2})\)").unwrap();
+
+    let mut notes = HashMap::new();
+    for element in document.select(&selector) {
+        let Some(version) = element.value().id() else {
+            continue;
+        };
+        let heading = trim_str(&element.text().collect::<String>());
+        let Some(date) = released.captures(&heading).map(|v| v[1].to_string()) else {
+            continue;
+        };
+        // A major release is a heading followed by its paragraph.
+        let summary = if element.value().name() == "h2" {
+            element
+                .next_siblings()
+                .filter_map(scraper::ElementRef::wrap)
+                .find(|v| paragraph.matches(v))
+                .map(|v| trim_str(&v.text().collect::<String>()))
+                .unwrap_or_default()
+        } else {
+            heading
+        };
+        notes.insert(
+            version.to_string(),
+            ReleaseNote {
+                date,
+                security: summary.contains("security fix"),
+                summary,
+            },
+        );
+    }
+    notes
+}
+
+fn build_message(release: &GoRelease) -> Message {
+    let mut title = format!("Go {}", release.version.trim_start_matches("go"));
+    if release.is_security() {
+        title.push_str(" [security]");
+    }
+    let content = format!(
+        "**Released**: {}\n{}",
+        release.note.date, release.note.summary
+    );
+    let color = if release.is_security() {
+        Color::Red
+    } else {
+        Color::Blue
+    };
+    Message::new(title, color)
+        .markdown(content)
+        .button("release notes", release.notes_url())
+        .button(
+            "download",
+            format!("https://go.dev/dl/#{}", release.version),
+        )
+}
+
+async fn send_request(url: &str, proxy: Option<String>) -> Result<String, reqwest::Error> {
+    info!(
+        "sending request to get go releases from {}, use proxy: {}",
+        url,
+        proxy.is_some()
+    );
+    let client: Client;
+    if let Some(proxy) = proxy {
+        let proxy = Proxy::https(proxy)?;
+        client = Client::builder().proxy(proxy).build()?;
+    } else {
+        client = reqwest::Client::new();
+    }
+    let resp = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    info!("get go releases from {} success", url);
+    Ok(resp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn downloads() -> Vec<GoDownload> {
+        serde_json::from_str(include_str!("../../fixtures/go_dl.json")).unwrap()
+    }
+
+    fn notes() -> HashMap<String, ReleaseNote> {
+        parse_release_history(include_str!("../../fixtures/go_release_history.html"))
+    }
+
+    #[test]
+    fn test_parse_release_history() {
+        let notes = notes();
+        assert_eq!(notes.len(), 5);
+        let note = &notes["go1.22.5"];
+        assert_eq!(note.date, "2024-07-02");
+        assert!(note.security);
+        assert!(note.summary.starts_with(
+            "go1.22.5 (released 2024-07-02) includes security fixes to the net/http package"
+        ));
+        assert!(!notes["go1.21.12"].security);
+        assert_eq!(
+            notes["go1.22.0"].summary,
+            "Go 1.22.0 is a major release of Go. Read the Go 1.22 Release Notes for more information."
+        );
+    }
+
+    #[test]
+    fn test_filter_releases() {
+        let source = ArticleSourceConfig::default();
+        let releases = filter_releases(None, &source, downloads(), notes());
+        let versions = releases
+            .iter()
+            .map(|v| v.version.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(versions, vec!["go1.21.12", "go1.22.5"]);
+        assert!(!releases[0].is_security());
+        assert!(releases[1].is_security());
+
+        // releases missing from the history are held
+        let mut notes = notes();
+        notes.remove("go1.22.5");
+        let releases = filter_releases(None, &source, downloads(), notes);
+        let versions = releases
+            .iter()
+            .map(|v| v.version.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(versions, vec!["go1.21.12"]);
+        assert!(filter_releases(None, &source, downloads(), HashMap::new()).is_empty());
+    }
+
+    #[test]
+    fn test_notes_url() {
+        let release = |version: &str| GoRelease {
+            version: version.to_string(),
+            note: ReleaseNote::default(),
+        };
+        assert_eq!(release("go1.23.0").notes_url(), "https://go.dev/doc/go1.23");
+        assert_eq!(
+            release("go1.22.5").notes_url(),
+            "https://go.dev/doc/devel/release#go1.22.5"
+        );
+        assert_eq!(
+            release("go1.20").notes_url(),
+            "https://go.dev/doc/devel/release#go1.20"
+        );
+    }
+
+    #[test]
+    fn test_build_message() {
+        let notes = notes();
+        let releases = filter_releases(None, &ArticleSourceConfig::default(), downloads(), notes);
+        let msg = build_message(&releases[1]);
+        assert_eq!(msg.title, "Go 1.22.5 [security]");
+        assert_eq!(msg.color, Color::Red);
+    }
+}
@@ -0,0 +1,386 @@
+use std::{fs::File, sync::LazyLock, thread, time::Duration};
+
+use anyhow::anyhow;
+use mail_parser::{mailbox, MessageParser};
+use scraper::{ElementRef, Html, Selector};
+use tracing::{error, info};
+
+use crate::{
+    canonical::normalize_url,
+    channels::newsletter::NAV_LINK_TEXTS,
+    chatgpt::build_feishu_content,
+    conf::MailboxConfig,
+    feeds,
+    notifier::{self, notify_all, Color, Message},
+    redis_base::{self, Redis},
+    sort_by_date, trim_str, truncate_str, DEFAULT_ONCE_POST_LIMIT,
+};
+
+/// The number of latest messages fetched from an IMAP server at a time.
+const IMAP_FETCH_LIMIT: usize = 20;
+
+/// Cards list at most this many links of a message.
+const MAX_ARTICLES: usize = 20;
+
+/// The max number of characters of an article description or a text body.
+const DESCRIPTION_PREVIEW_LEN: usize = 200;
+
+/// The max number of characters of a mail sent to be summarised.
+const MAX_REQ_TEXT_LEN: usize = 8000;
+
+static LINK_SELECTOR: LazyLock<Selector> = LazyLock::new(|| Selector::parse("a[href]").unwrap());
+static BODY_SELECTOR: LazyLock<Selector> = LazyLock::new(|| Selector::parse("body").unwrap());
+
+/// An email newsletter.
+#[derive(Debug, Default, PartialEq)]
+pub struct Mail {
+    pub id: String,
+    pub subject: String,
+    pub from: String,
+    pub date: String,
+    pub articles: Vec<Article>,
+    pub text: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Article {
+    pub title: String,
+    pub url: String,
+    pub description: String,
+}
+
+pub async fn send_feishu_msg(
+    redis: &redis_base::Redis,
+    conf: &MailboxConfig,
+    openai_api_key: Option<String>,
+    openai_host: Option<String>,
+    proxy: Option<String>,
+) -> anyhow::Result<()> {
+    info!("start reading mailbox {}", conf.name);
+    // the mailbox is read with blocking file and socket I/O
+    let mails = {
+        let conf = conf.clone();
+        tokio::task::spawn_blocking(move || read_mails(&conf)).await??
+    };
+    let mails = filter_mails(Some(redis), conf, mails);
+    info!("read mailbox {} success, mails: {}", conf.name, mails.len());
+    let notifiers = notifier::from_source(redis, &conf.name, &conf.source, None);
+    for mail in mails {
+        thread::sleep(Duration::from_secs(3));
+        let content = build_content(
+            &conf.name,
+            &mail,
+            openai_api_key.clone(),
+            openai_host.clone(),
+            proxy.clone(),
+        )
+        .await;
+        let msg = Message::new(mail_title(&conf.name, &mail), Color::Violet).markdown(content);
+        if let Err(e) = notify_all(&notifiers, &msg).await {
+            error!("{}", e);
+        }
+    }
+    info!("finish reading mailbox {}", conf.name);
+    Ok(())
+}
+
+/// Reads the raw messages of the configured Maildir, mbox or IMAP mailbox
+/// and parses them, unparsable messages are skipped.
+fn read_mails(conf: &MailboxConfig) -> anyhow::Result<Vec<Mail>> {
+    let raws: Vec<Vec<u8>> = if let Some(path) = &conf.maildir {
+        mailbox::maildir::MessageIterator::new(path)?
+            .filter_map(|v| v.map_err(|e| error!("read {} failed: {}", path, e)).ok())
+            .map(|v| v.unwrap_contents())
+            .collect()
+    } else if let Some(path) = &conf.mbox {
+        mailbox::mbox::MessageIterator::new(File::open(path)?)
+            .filter_map(|v| v.map_err(|e| error!("read {} failed: {:?}", path, e)).ok())
+            .map(|v| v.unwrap_contents())
+            .collect()
+    } else if let Some(imap) = &conf.imap {
+        feeds::fetch_messages(imap, IMAP_FETCH_LIMIT)?
+    } else {
+        return Err(anyhow!(
+            "mailbox {} sets none of `maildir`, `mbox` and `imap`",
+            conf.name
+        ));
+    };
+    Ok(raws.iter().filter_map(|v| parse_mail(v)).collect())
+}
+
+fn filter_mails(
+    redis: Option<&redis_base::Redis>,
+    conf: &MailboxConfig,
+    mut mails: Vec<Mail>,
+) -> Vec<Mail> {
+    let mut once_post_limit = conf.source.once_post_limit;
+    if once_post_limit == 0 {
+        once_post_limit = DEFAULT_ONCE_POST_LIMIT
+    }
+    sort_by_date(&mut mails, conf.source.order, |v| &v.date);
+    let key = Redis::mailbox_key(&conf.name);
+    mails
+        .into_iter()
+        .filter(|v| match &conf.from {
+            Some(from) => v.from.to_lowercase().contains(&from.to_lowercase()),
+            None => true,
+        })
+        .filter(|v| match redis {
+            Some(r) => r.setnx(&key, &v.id),
+            None => true,
+        })
+        .take(once_post_limit as usize)
+        .collect()
+}
+
+pub fn parse_mail(raw: &[u8]) -> Option<Mail> {
+    let message = MessageParser::default().parse(raw)?;
+    let subject = message.subject().unwrap_or_default().to_string();
+    let date = message.date().map(|v| v.to_rfc3339()).unwrap_or_default();
+    let from = message
+        .from()
+        .and_then(|v| v.first())
+        .map(|v| match (v.name(), v.address()) {
+            (Some(name), Some(address)) => format!("{} <{}>", name, address),
+            (name, address) => name.or(address).unwrap_or_default().to_string(),
+        })
+        .unwrap_or_default();
+    let id = match message.message_id() {
+        Some(v) => v.to_string(),
+        None => format!("{}|{}|{}", from, date, subject),
+    };
+    let (articles, text) = match message.body_html(0) {
+        Some(html) => parse_html_body(&html),
+        None => (vec![], trim_str(&message.body_text(0).unwrap_or_default())),
+    };
+    Some(Mail {
+        id,
+        subject,
+        from,
+        date,
+        articles,
+        text,
+    })
+}
+
+/// Extracts the links of an HTML body with the text around them, and the
+/// whole text of the body.
+pub fn parse_html_body(html: &str) -> (Vec<Article>, String) {
+    let document = Html::parse_document(html);
+    let mut articles: Vec<Article> = vec![];
+    for a in document.select(&LINK_SELECTOR) {
+        let href = a.value().attr("href").unwrap_or_default();
+        if !href.starts_with("http") {
+            continue;
+        }
+        let title = element_text(a);
+        let lower = title.to_lowercase();
+        if title.is_empty() || NAV_LINK_TEXTS.contains(&lower.as_str()) {
+            continue;
+        }
+        let url = normalize_url(href);
+        if articles.iter().any(|v| v.url == url) {
+            continue;
+        }
+        let description = a
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .find(|v| ["p", "li", "td"].contains(&v.value().name()))
+            .map(element_text)
+            .filter(|v| v != &title)
+            .map(|v| truncate_str(&v, DESCRIPTION_PREVIEW_LEN))
+            .unwrap_or_default();
+        articles.push(Article {
+            title,
+            url,
+            description,
+        });
+        if articles.len() == MAX_ARTICLES {
+            break;
+        }
+    }
+    (articles, body_text(&document))
+}
+
+/// The text of the body, without the contents of `style` and `script`.
+fn body_text(document: &Html) -> String {
+    let Some(body) = document.select(&BODY_SELECTOR).next() else {
+        return String::new();
+    };
+    let texts = body
+        .descendants()
+        .filter_map(|node| node.value().as_text().map(|v| (node, v)))
+        .filter(|(node, _)| {
+            !node
+                .ancestors()
+                .filter_map(ElementRef::wrap)
+                .any(|v| ["style", "script", "head"].contains(&v.value().name()))
+        })
+        .map(|(_, v)| &**v)
+        .collect::<Vec<_>>();
+    trim_str(&texts.join(" "))
+}
+
+fn element_text(element: ElementRef) -> String {
+    trim_str(&element.text().collect::<Vec<_>>().join(" "))
+}
+
+/// Builds the card header, e.g. `[Changelog News] Changelog News #105 - 2024-07-25`.
+fn mail_title(name: &str, mail: &Mail) -> String {
+    let date = mail.date.split('T').next().unwrap_or_default();
+    if date.is_empty() {
+        format!("[{}] {}", name, mail.subject)
+    } else {
+        format!("[{}] {} - {}", name, mail.subject, date)
+    }
+}
+
+fn build_links(mail: &Mail) -> String {
+    if mail.articles.is_empty() {
+        return truncate_str(&mail.text, DESCRIPTION_PREVIEW_LEN);
+    }
+    mail.articles
+        .iter()
+        .map(|v| {
+            if v.description.is_empty() {
+                format!("[{}]({})\n", v.title, v.url)
+            } else {
+                format!("[{}]({})\n{}\n", v.title, v.url, v.description)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("---\n")
+}
+
+async fn build_content(
+    name: &str,
+    mail: &Mail,
+    openai_api_key: Option<String>,
+    openai_host: Option<String>,
+    proxy: Option<String>,
+) -> String {
+    let mut content = build_links(mail);
+    let c = build_feishu_content(
+        openai_api_key,
+        openai_host,
+        proxy,
+        build_req_content(name, mail),
+    )
+    .await;
+    content.push_str(&c);
+    content
+}
+
+fn build_req_content(name: &str, mail: &Mail) -> String {
+    let mut res = String::with_capacity(mail.text.len() + 128);
+    res.push_str(&format!("这是邮件订阅 {} 的一期：{}\n", name, mail.subject));
+    res.push_str(&truncate_str(&mail.text, MAX_REQ_TEXT_LEN));
+    res.push('\n');
+    res.push_str("请你使用中文总结这期邮件的主要内容，不要超过150个字。\n");
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::conf::ArticleSourceConfig;
+
+    use super::*;
+
+    fn conf() -> MailboxConfig {
+        MailboxConfig {
+            name: "Changelog News".to_string(),
+            maildir: Some("fixtures/maildir".to_string()),
+            mbox: None,
+            imap: None,
+            from: None,
+            source: ArticleSourceConfig::default(),
+        }
+    }
+
+    #[test]
+    fn test_read_maildir() {
+        let mails = read_mails(&conf()).unwrap();
+        assert_eq!(mails.len(), 1);
+        let mail = &mails[0];
+        assert_eq!(mail.id, "20240725083000.1234@changelog.com");
+        assert_eq!(mail.subject, "Changelog News #105: Rust in the kernel");
+        assert_eq!(mail.from, "Changelog News <hello@changelog.com>");
+        assert_eq!(mail.date, "2024-07-25T08:30:00Z");
+        let titles = mail
+            .articles
+            .iter()
+            .map(|v| v.title.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            titles,
+            vec![
+                "Rust in the Linux kernel",
+                "Tokio 1.39 is out",
+                "Announcing Rust 1.80"
+            ]
+        );
+        assert_eq!(mail.articles[0].url, "https://lwn.net/Articles/980000");
+        assert_eq!(
+            mail.articles[0].description,
+            "Rust in the Linux kernel — a status update on the Rust for Linux project and the drivers being merged."
+        );
+        assert!(mail.articles[1].description.is_empty());
+        // the style and script of the page are not text
+        assert!(mail
+            .text
+            .starts_with("View in browser Rust in the Linux kernel"));
+        assert!(!mail.text.contains("font-family") && !mail.text.contains("window.track"));
+    }
+
+    #[test]
+    fn test_read_mbox() {
+        let conf = MailboxConfig {
+            maildir: None,
+            mbox: Some("fixtures/newsletters.mbox".to_string()),
+            ..conf()
+        };
+        let mails = read_mails(&conf).unwrap();
+        assert_eq!(mails.len(), 2);
+        assert_eq!(mails[0].articles.len(), 1);
+        assert_eq!(mails[1].subject, "Your invoice");
+        assert!(mails[1].articles.is_empty());
+        assert!(mails[1].text.starts_with("Your invoice is attached."));
+
+        let conf = MailboxConfig {
+            from: Some("HELLO@changelog.com".to_string()),
+            ..conf
+        };
+        let mails = filter_mails(None, &conf, read_mails(&conf).unwrap());
+        assert_eq!(mails.len(), 1);
+        assert_eq!(mails[0].subject, "Changelog News #104: Zig and Go");
+    }
+
+    #[test]
+    fn test_read_mails_without_location() {
+        let conf = MailboxConfig {
+            maildir: None,
+            ..conf()
+        };
+        assert!(read_mails(&conf).is_err());
+    }
+
+    #[test]
+    fn test_mail_title_and_links() {
+        let mails = read_mails(&conf()).unwrap();
+        assert_eq!(
+            mail_title("Changelog News", &mails[0]),
+            "[Changelog News] Changelog News #105: Rust in the kernel - 2024-07-25"
+        );
+        assert!(build_links(&mails[0]).starts_with(
+            "[Rust in the Linux kernel](https://lwn.net/Articles/980000)\nRust in the Linux kernel — "
+        ));
+
+        let mail = Mail {
+            text: "a".repeat(20000),
+            ..Default::default()
+        };
+        assert!(
+            build_req_content("Changelog News", &mail).chars().count() < MAX_REQ_TEXT_LEN + 200
+        );
+    }
+}
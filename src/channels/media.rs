@@ -0,0 +1,313 @@
+use std::{thread, time::Duration};
+
+use reqwest::{Client, Proxy};
+use scraper::Html;
+use tracing::{error, info};
+
+use crate::{
+    conf::{FeishuAppConfig, MediaFeedConfig},
+    feeds::{Atom, Entry, Feed, Item},
+    notifier::{self, notify_all, Color, Message},
+    redis_base::{self, Redis},
+    sort_by_date, trim_str, truncate_str, DEFAULT_ONCE_POST_LIMIT,
+};
+
+/// The max number of characters of the description shown in the card.
+const DESCRIPTION_PREVIEW_LEN: usize = 300;
+
+/// A video of a YouTube Atom feed or an episode of a podcast RSS feed.
+#[derive(Debug, Default, PartialEq)]
+pub struct MediaItem {
+    pub id: String,
+    pub title: String,
+    pub url: String,
+    pub date: String,
+    pub thumbnail: Option<String>,
+    pub duration: Option<String>,
+    pub description: String,
+    pub audio: bool,
+}
+
+impl From<Entry> for MediaItem {
+    fn from(entry: Entry) -> Self {
+        let date = entry.date().to_string();
+        let media = entry.media.unwrap_or_default();
+        let description = if media.description.is_empty() {
+            html_to_text(&entry.summary)
+        } else {
+            media.description.trim().to_string()
+        };
+        MediaItem {
+            id: entry.id,
+            title: entry.title,
+            url: entry.link.href,
+            date,
+            thumbnail: media.thumbnail.map(|v| v.url),
+            duration: media
+                .content
+                .and_then(|v| v.duration)
+                .and_then(|v| format_duration(&v)),
+            description,
+            audio: false,
+        }
+    }
+}
+
+impl From<Item> for MediaItem {
+    fn from(item: Item) -> Self {
+        let duration = item
+            .duration
+            .or(item.media_content.and_then(|v| v.duration))
+            .and_then(|v| format_duration(&v));
+        MediaItem {
+            id: if item.guid.is_empty() {
+                item.link.clone()
+            } else {
+                item.guid
+            },
+            title: item.title,
+            url: item.link,
+            date: item.pub_date,
+            thumbnail: item.image.map(|v| v.href).or(item.thumbnail.map(|v| v.url)),
+            duration,
+            description: html_to_text(&item.description),
+            audio: item
+                .enclosure
+                .is_some_and(|v| v.mime_type.starts_with("audio/")),
+        }
+    }
+}
+
+pub async fn send_feishu_msg(
+    redis: &redis_base::Redis,
+    conf: &MediaFeedConfig,
+    feishu_app: Option<&FeishuAppConfig>,
+    proxy: Option<String>,
+) -> anyhow::Result<()> {
+    info!("start fetching {}", conf.name);
+    let data = send_request(&conf.url, proxy).await?;
+    let items = filter_items(Some(redis), conf, parse_items(&data)?);
+    info!("fetch {} success, items: {}", conf.name, items.len());
+    // Feishu cards only show uploaded images, which needs the credentials of an app.
+    let notifiers = notifier::from_source(redis, &conf.name, &conf.source, feishu_app);
+    for item in items {
+        thread::sleep(Duration::from_secs(3));
+        if let Err(e) = notify_all(&notifiers, &build_message(&item)).await {
+            error!("{}", e);
+        }
+    }
+    info!("finish fetching {}", conf.name);
+    Ok(())
+}
+
+/// Reads a YouTube Atom feed or a podcast RSS feed.
+fn parse_items(data: &str) -> anyhow::Result<Vec<MediaItem>> {
+    let atom_err = match Atom::parse(data) {
+        Ok(atom) => return Ok(atom.entry.into_iter().map(MediaItem::from).collect()),
+        Err(e) => e,
+    };
+    // a malformed Atom feed would only report the RSS error otherwise
+    let feed = Feed::parse(data).map_err(|e| {
+        anyhow::anyhow!(
+            "neither an Atom feed ({}) nor an RSS feed ({})",
+            atom_err,
+            e
+        )
+    })?;
+    Ok(feed
+        .channel
+        .items
+        .into_iter()
+        .map(MediaItem::from)
+        .collect())
+}
+
+fn filter_items(
+    redis: Option<&redis_base::Redis>,
+    conf: &MediaFeedConfig,
+    mut items: Vec<MediaItem>,
+) -> Vec<MediaItem> {
+    let mut once_post_limit = conf.source.once_post_limit;
+    if once_post_limit == 0 {
+        once_post_limit = DEFAULT_ONCE_POST_LIMIT
+    }
+    sort_by_date(&mut items, conf.source.order, |v| &v.date);
+    let key = Redis::media_key(&conf.name);
+    items
+        .into_iter()
+        .filter(|v| match redis {
+            Some(r) => r.setnx(&key, &v.id),
+            None => true,
+        })
+        .take(once_post_limit as usize)
+        .collect()
+}
+
+/// Formats `3125` seconds or `1:04:37` as `1:04:37`, `52:05` as `52:05`.
+fn format_duration(duration: &str) -> Option<String> {
+    let mut secs = 0u64;
+    for part in duration.trim().split(':') {
+        secs = secs * 60 + part.parse::<u64>().ok()?;
+    }
+    let (h, m, s) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    if h > 0 {
+        Some(format!("{}:{:02}:{:02}", h, m, s))
+    } else {
+        Some(format!("{}:{:02}", m, s))
+    }
+}
+
+fn html_to_text(html: &str) -> String {
+    trim_str(
+        &Html::parse_fragment(html)
+            .root_element()
+            .text()
+            .collect::<Vec<_>>()
+            .join(" "),
+    )
+}
+
+fn build_content(item: &MediaItem) -> String {
+    let mut content = String::with_capacity(512);
+    if let Some(duration) = &item.duration {
+        content.push_str(&format!("**Duration**: {}\n", duration));
+    }
+    if !item.description.is_empty() {
+        content.push_str(&truncate_str(&item.description, DESCRIPTION_PREVIEW_LEN));
+    }
+    content
+}
+
+fn build_message(item: &MediaItem) -> Message {
+    let mut msg = Message::new(item.title.as_str(), Color::Indigo).date(item.date.as_str());
+    if let Some(url) = &item.thumbnail {
+        msg = msg.image(url.as_str(), "thumbnail");
+    }
+    msg.markdown(build_content(item)).button(
+        if item.audio { "listen" } else { "watch" },
+        item.url.as_str(),
+    )
+}
+
+async fn send_request(url: &str, proxy: Option<String>) -> Result<String, reqwest::Error> {
+    info!(
+        "sending request to get media feed from {}, use proxy: {}",
+        url,
+        proxy.is_some()
+    );
+    let client: Client;
+    if let Some(proxy) = proxy {
+        let proxy = Proxy::https(proxy)?;
+        client = Client::builder().proxy(proxy).build()?;
+    } else {
+        client = reqwest::Client::new();
+    }
+    let resp = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    info!("get media feed from {} success", url);
+    Ok(resp)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        conf::ArticleSourceConfig,
+        notifier::{Button, Element},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration("3125"), Some("52:05".to_string()));
+        assert_eq!(format_duration("1:04:37"), Some("1:04:37".to_string()));
+        assert_eq!(format_duration("04:37"), Some("4:37".to_string()));
+        assert_eq!(format_duration("PT1H"), None);
+    }
+
+    #[test]
+    fn test_parse_youtube_items() {
+        let items = parse_items(include_str!("../../fixtures/youtube.xml")).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].id, "yt:video:9fx4v2pQ3qg");
+        assert_eq!(items[0].url, "https://www.youtube.com/watch?v=9fx4v2pQ3qg");
+        assert_eq!(
+            items[0].thumbnail.as_deref(),
+            Some("https://i4.ytimg.com/vi/9fx4v2pQ3qg/hqdefault.jpg")
+        );
+        assert_eq!(items[0].duration, None);
+        assert!(!items[0].audio);
+    }
+
+    #[test]
+    fn test_parse_podcast_items() {
+        let items = parse_items(include_str!("../../fixtures/podcast.xml")).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].id, "changelog.com/2/2593");
+        assert_eq!(items[0].duration.as_deref(), Some("1:04:37"));
+        assert_eq!(items[1].duration.as_deref(), Some("52:05"));
+        assert_eq!(
+            items[1].thumbnail.as_deref(),
+            Some("https://cdn.changelog.com/uploads/covers/go-time-329.png")
+        );
+        assert_eq!(
+            items[0].description,
+            "Natalie & Angelica talk with the organizers of GopherCon about the community."
+        );
+        assert!(items[0].audio);
+    }
+
+    #[test]
+    fn test_parse_items_reports_both_errors() {
+        // a YouTube feed missing the `id` of its entry
+        let data = r#"<feed xmlns="http://www.w3.org/2005/Atom"><title>RustConf</title><entry><title>a</title></entry></feed>"#;
+        let err = parse_items(data).unwrap_err().to_string();
+        assert!(err.starts_with("neither an Atom feed ("), "{}", err);
+        assert!(err.contains("RSS feed ("), "{}", err);
+    }
+
+    #[test]
+    fn test_filter_items() {
+        let conf = MediaFeedConfig {
+            name: "Go Time".to_string(),
+            url: "https://changelog.com/gotime/feed".to_string(),
+            source: ArticleSourceConfig {
+                once_post_limit: 1,
+                ..Default::default()
+            },
+        };
+        let items = parse_items(include_str!("../../fixtures/podcast.xml")).unwrap();
+        let items = filter_items(None, &conf, items);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].title, "Is Go evolving in the wrong direction?");
+    }
+
+    #[test]
+    fn test_build_message() {
+        let items = parse_items(include_str!("../../fixtures/podcast.xml")).unwrap();
+        let msg = build_message(&items[0]);
+        assert_eq!(msg.date.as_deref(), Some("Wed, 18 Sep 2024 20:00:00 +0000"));
+        assert_eq!(
+            msg.elements,
+            vec![
+                Element::Image {
+                    url: "https://cdn.changelog.com/uploads/covers/go-time-330.png".to_string(),
+                    alt: "thumbnail".to_string(),
+                },
+                Element::Markdown(
+                    "**Duration**: 1:04:37\nNatalie & Angelica talk with the organizers of GopherCon about the community."
+                        .to_string()
+                ),
+                Element::Buttons(vec![Button {
+                    text: "listen".to_string(),
+                    url: "https://changelog.com/gotime/330".to_string(),
+                }]),
+            ]
+        );
+    }
+}
@@ -1,5 +1,14 @@
+pub mod advisories;
+pub mod crates_io;
+pub mod github_releases;
 pub mod go_blog;
+pub mod go_releases;
 pub mod go_weekly;
+pub mod mailbox;
+pub mod media;
+pub mod newsletter;
 pub mod redis_blog;
 pub mod rust_blog;
 pub mod rust_inside_blog;
+pub mod scrape;
+pub mod this_week_in_rust;
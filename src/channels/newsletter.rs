@@ -0,0 +1,754 @@
+use crate::{
+    canonical::{host_of, normalize_url, redirect_client, resolve_redirect},
+    chatgpt::build_feishu_content,
+    conf::{ArticleSourceConfig, PostOrder},
+    feeds::Feed,
+    parse_date,
+    redis_base::Redis,
+    sort_by_date, trim_str, DEFAULT_ONCE_POST_LIMIT,
+};
+use core::fmt;
+use std::{collections::HashSet, mem, sync::LazyLock, thread, time::Duration, vec};
+
+use ego_tree::{NodeId, NodeRef};
+use regex::Regex;
+use scraper::{ElementRef, Html, Node, Selector};
+use tracing::{error, info};
+
+use crate::{
+    conf::NewsletterConfig,
+    notifier::{self, notify_all, Color, Message},
+    redis_base,
+};
+
+/// Texts of the links to the newsletter itself, rather than to articles.
+pub(crate) const NAV_LINK_TEXTS: [&str; 6] = [
+    "unsub",
+    "unsubscribe",
+    "web version",
+    "read on the web",
+    "view in browser",
+    "prefer to read on the web?",
+];
+
+/// Cells with a short text and no links are section headings.
+const MAX_HEADING_LEN: usize = 80;
+
+/// A newsletter built on the Cooperpress template, such as Golang Weekly,
+/// Postgres Weekly or Node Weekly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Newsletter {
+    // the name used in logs and in the OpenAI prompt.
+    pub name: String,
+    pub rss_url: String,
+    // the domain of the `https://{domain}/link/NNN/rss` article links.
+    pub link_domain: String,
+    // the hash in Redis that remembers the pushed article links.
+    pub redis_key: String,
+}
+
+impl Newsletter {
+    pub fn new(name: &str, rss_url: &str, link_domain: &str) -> Self {
+        Newsletter {
+            name: name.to_string(),
+            rss_url: rss_url.to_string(),
+            link_domain: link_domain.to_string(),
+            redis_key: format!(
+                "{}:{}",
+                Redis::HSET_NEWSLETTER_KEY_PREFIX,
+                name.to_lowercase().replace(' ', "-")
+            ),
+        }
+    }
+
+    /// Returns the prefix of the links to the newsletter's articles.
+    pub fn link_marker(&self) -> String {
+        format!("https://{}/link/", self.link_domain)
+    }
+
+    pub fn home_url(&self) -> String {
+        format!("https://{}/", self.link_domain)
+    }
+}
+
+impl From<&NewsletterConfig> for Newsletter {
+    fn from(conf: &NewsletterConfig) -> Self {
+        Newsletter::new(&conf.name, &conf.rss_url, &conf.link_domain)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Article {
+    pub url: String,
+    pub title: String,
+    pub description: String,
+    pub author: String,
+    // the article url behind the tracking `url`, if it has been resolved.
+    pub resolved_url: Option<String>,
+    // the heading of the section the article is listed in, empty for the lead section.
+    pub section: String,
+    pub sponsored: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Issue {
+    pub number: Option<u32>,
+    pub sections: Vec<Section>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Section {
+    pub heading: String,
+    pub articles: Vec<Article>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WeeklyArticle {
+    pub date: String,
+    pub issue: Option<u32>,
+    pub articles: Vec<Article>,
+}
+
+impl Issue {
+    pub fn article_count(&self) -> usize {
+        self.sections.iter().map(|v| v.articles.len()).sum()
+    }
+
+    pub fn into_articles(self) -> Vec<Article> {
+        self.sections.into_iter().flat_map(|v| v.articles).collect()
+    }
+
+    fn push_section(&mut self, section: Section) {
+        if !section.articles.is_empty() {
+            self.sections.push(section);
+        }
+    }
+}
+
+impl Section {
+    fn new(heading: String) -> Self {
+        Section {
+            heading,
+            articles: vec![],
+        }
+    }
+
+    /// Classifieds and sponsor sections only list paid items.
+    pub fn is_sponsored(&self) -> bool {
+        let heading = self.heading.to_lowercase();
+        heading.contains("classified") || heading.contains("sponsor")
+    }
+}
+
+impl fmt::Display for Article {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let resolved = self.resolved_url.as_deref();
+        write!(f, "**[{}]({})**", self.title, resolved.unwrap_or(&self.url))?;
+        if let Some(host) = resolved.and_then(host_of) {
+            write!(f, " ({})", host)?;
+        }
+        if self.sponsored {
+            write!(f, " [sponsor]")?;
+        }
+        write!(f, ": {}", self.description)?;
+        if !self.author.is_empty() {
+            write!(f, " (_{}_)", self.author)?;
+        }
+        writeln!(f)
+    }
+}
+
+pub async fn send_feishu_msg(
+    redis: &redis_base::Redis,
+    newsletter: &Newsletter,
+    source: &ArticleSourceConfig,
+    openai_api_key: Option<String>,
+    openai_host: Option<String>,
+    proxy: Option<String>,
+) -> anyhow::Result<()> {
+    info!("start fetching {} issues", newsletter.name);
+    let (rss, articles) = get_rss_articles(Some(redis), newsletter, source).await?;
+    info!(
+        "fetch {} issues success, articles: {}",
+        newsletter.name,
+        articles.len()
+    );
+    let notifiers = notifier::from_source(redis, &newsletter.name, source, None);
+    for (i, wa) in articles.into_iter().enumerate() {
+        if wa.articles.is_empty() {
+            continue;
+        }
+        if i > 0 {
+            thread::sleep(Duration::from_secs(3));
+        }
+        let title = issue_title(&rss.channel.title, &wa);
+        let content = build_content(
+            &newsletter.name,
+            wa.articles,
+            openai_api_key.clone(),
+            openai_host.clone(),
+            proxy.clone(),
+        )
+        .await;
+        info!("build {} content success", newsletter.name);
+        let msg = Message::new(title, Color::Green)
+            .markdown(content)
+            .button("More issues", newsletter.home_url());
+        if let Err(e) = notify_all(&notifiers, &msg).await {
+            error!("{}", e);
+        }
+    }
+    info!("finish fetching {} issues", newsletter.name);
+    Ok(())
+}
+
+/// Parses the html of a newsletter issue, keeping the articles linked
+/// through `link_marker`.
+///
+/// The issue is a list of `<table>` blocks, each leaf `<td>` is one of:
+/// a heading (short text without links), a featured article (title link
+/// in a `<span>`, description and author paragraphs), a list of brief
+/// items (`<li>`) or paragraphs each led by a link. Anything else is
+/// skipped, unexpected markup never panics.
+///
+/// A heading applies until the next one in the same enclosing cell, so the
+/// headings of nested blocks such as classifieds do not leak out of them.
+pub fn parse_issue(desc: &str, link_marker: &str) -> Issue {
+    let document = Html::parse_document(desc);
+
+    let mut issue = Issue::default();
+    let mut section = Section::default();
+    // The headings in effect, with the cell each of them is scoped to.
+    let mut headings: Vec<(String, Option<NodeId>)> = vec![];
+    let mut seen = HashSet::new();
+    for td in document.select(&TD_SELECTOR) {
+        // Only leaf cells, the outer ones just wrap other tables.
+        if td.select(&TABLE_SELECTOR).next().is_some() {
+            continue;
+        }
+        let depth = headings.len();
+        while let Some((_, Some(scope))) = headings.last() {
+            if td.ancestors().any(|v| v.id() == *scope) {
+                break;
+            }
+            headings.pop();
+        }
+        if headings.len() != depth {
+            let heading = headings.last().map(|v| v.0.clone()).unwrap_or_default();
+            issue.push_section(mem::replace(&mut section, Section::new(heading)));
+        }
+
+        let text = element_text(td);
+        if text.is_empty() {
+            continue;
+        }
+        if issue.number.is_none() {
+            issue.number = parse_issue_number(&text);
+        }
+        let started = issue.article_count() > 0 || !section.articles.is_empty();
+
+        // A label such as `QUICK BITS:` only heads the items of its own cell.
+        if let Some(label) = leading_label(td) {
+            headings.push((label.clone(), Some(td.id())));
+            issue.push_section(mem::replace(&mut section, Section::new(label)));
+        }
+
+        let articles = resolve_cell(td, &section, link_marker);
+        if articles.is_empty() {
+            // Text before the first article is the masthead, not a section.
+            if started && is_heading(td, &text) {
+                let heading = text.trim_end_matches(':').trim().to_string();
+                let scope = td
+                    .ancestors()
+                    .filter_map(ElementRef::wrap)
+                    .find(|v| v.value().name() == "td")
+                    .map(|v| v.id());
+                if headings.last().is_some_and(|v| v.1 == scope) {
+                    headings.pop();
+                }
+                headings.push((heading.clone(), scope));
+                issue.push_section(mem::replace(&mut section, Section::new(heading)));
+            }
+            continue;
+        }
+        for article in articles {
+            if seen.insert(article.url.clone()) {
+                section.articles.push(article);
+            }
+        }
+    }
+    issue.push_section(section);
+    issue
+}
+
+/// Returns the text of the first paragraph of `td` if it is a short label
+/// without links, followed by a list of items.
+fn leading_label(td: ElementRef) -> Option<String> {
+    td.select(&LI_SELECTOR).next()?;
+    let first = td.select(&P_SELECTOR).next()?;
+    if first.select(&A_SELECTOR).next().is_some() || first.ancestors().any(is_li) {
+        return None;
+    }
+    let text = element_text(first);
+    let label = text.trim_end_matches(':').trim();
+    if label.is_empty() || label.chars().count() > MAX_HEADING_LEN {
+        return None;
+    }
+    Some(label.to_string())
+}
+
+fn is_li(node: NodeRef<Node>) -> bool {
+    ElementRef::wrap(node).is_some_and(|v| v.value().name() == "li")
+}
+
+fn resolve_cell(td: ElementRef, section: &Section, link_marker: &str) -> Vec<Article> {
+    let paragraphs = td.select(&P_SELECTOR).collect::<Vec<_>>();
+    let sponsored_section = section.is_sponsored();
+
+    // Featured article:
+    // <td>
+    //   <p>
+    //     <span>
+    //       <a href="{link}">{title}</a>
+    //     </span>
+    //     - {description}
+    //   </p>
+    //   <p>{author}</p>
+    // </td>
+    if let Some(first) = paragraphs.first() {
+        if let Some(a) = first.select(&SPAN_A_SELECTOR).next() {
+            let Some(link) = article_link(a, link_marker) else {
+                return vec![];
+            };
+            let mut description = String::new();
+            for node in first.children() {
+                if let Some(text_node) = node.value().as_text() {
+                    description.push_str(text_node.trim());
+                }
+            }
+            let author = if paragraphs.len() > 1 {
+                element_text(paragraphs[paragraphs.len() - 1])
+            } else {
+                String::new()
+            };
+            let sponsored = sponsored_section || author.to_lowercase().contains("sponsor");
+            return vec![Article {
+                url: trim_str(link),
+                title: trim_str(&a.inner_html()),
+                description: trim_str(&description),
+                author: trim_str(&strip_sponsor_mark(&author)),
+                resolved_url: None,
+                section: section.heading.clone(),
+                sponsored,
+            }];
+        }
+    }
+
+    // Brief items, one per <li>, otherwise one per <p>:
+    // <li><p><a href="{link}">{title}</a> - {description}</p></li>
+    // <p><a href="{link}">{title}</a> - {description} <cite>{author}</cite></p>
+    let mut items = td.select(&LI_SELECTOR).collect::<Vec<_>>();
+    if items.is_empty() {
+        items = paragraphs;
+    }
+    items
+        .into_iter()
+        .filter_map(|item| resolve_brief_item(item, section, sponsored_section, link_marker))
+        .collect()
+}
+
+fn resolve_brief_item(
+    item: ElementRef,
+    section: &Section,
+    sponsored: bool,
+    link_marker: &str,
+) -> Option<Article> {
+    let a = item
+        .select(&A_SELECTOR)
+        .find(|a| article_link(*a, link_marker).is_some())?;
+    let link = article_link(a, link_marker)?;
+    let title = element_text(a);
+    if title.is_empty() {
+        return None;
+    }
+    let author = item
+        .select(&CITE_SELECTOR)
+        .next()
+        .map(element_text)
+        .unwrap_or_default();
+    let text = element_text(item);
+    let text = text.strip_suffix(author.as_str()).unwrap_or(&text).trim();
+    let description = match text.find(title.as_str()) {
+        // Drop the leading emoji and title, keep the rest as description.
+        Some(i) if text[..i].chars().all(|c| !c.is_alphanumeric()) => &text[i + title.len()..],
+        _ => text,
+    };
+    Some(Article {
+        url: trim_str(link),
+        title,
+        description: trim_str(description),
+        author: trim_str(&strip_sponsor_mark(&author)),
+        resolved_url: None,
+        section: section.heading.clone(),
+        sponsored: sponsored || author.to_lowercase().contains("sponsor"),
+    })
+}
+
+/// Returns the href of `a` if it links to an article, rather than to an
+/// image or the newsletter's own pages.
+fn article_link<'a>(a: ElementRef<'a>, link_marker: &str) -> Option<&'a str> {
+    let href = a.value().attr("href")?;
+    if !href.contains(link_marker) {
+        return None;
+    }
+    let text = element_text(a).to_lowercase();
+    if NAV_LINK_TEXTS.contains(&text.as_str()) {
+        return None;
+    }
+    Some(href)
+}
+
+fn is_heading(td: ElementRef, text: &str) -> bool {
+    td.select(&A_SELECTOR).next().is_none()
+        && text.chars().count() <= MAX_HEADING_LEN
+        && parse_issue_number(text).is_none()
+}
+
+// The selectors and regexes run for every cell of an issue, so they are
+// compiled once.
+static TD_SELECTOR: LazyLock<Selector> = LazyLock::new(|| Selector::parse("td").unwrap());
+static TABLE_SELECTOR: LazyLock<Selector> = LazyLock::new(|| Selector::parse("table").unwrap());
+static P_SELECTOR: LazyLock<Selector> = LazyLock::new(|| Selector::parse("p").unwrap());
+static LI_SELECTOR: LazyLock<Selector> = LazyLock::new(|| Selector::parse("li").unwrap());
+static A_SELECTOR: LazyLock<Selector> = LazyLock::new(|| Selector::parse("a").unwrap());
+static SPAN_A_SELECTOR: LazyLock<Selector> = LazyLock::new(|| Selector::parse("span a").unwrap());
+static CITE_SELECTOR: LazyLock<Selector> = LazyLock::new(|| Selector::parse("cite").unwrap());
+static ISSUE_NUMBER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"#[\u{200B}\s]*(\d+)").unwrap());
+static SPONSOR_MARK_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\bsponsor(ed)?\b").unwrap());
+
+fn parse_issue_number(text: &str) -> Option<u32> {
+    ISSUE_NUMBER_RE
+        .captures(text)?
+        .get(1)?
+        .as_str()
+        .parse()
+        .ok()
+}
+
+fn strip_sponsor_mark(author: &str) -> String {
+    SPONSOR_MARK_RE.replace_all(author, "").to_string()
+}
+
+fn element_text(element: ElementRef) -> String {
+    trim_str(&element.text().collect::<Vec<_>>().join(" "))
+}
+
+/// Fetches the unsent articles of the newsletter.
+///
+/// Only the first issue with unsent articles is returned, unless
+/// `catch_up` is set, then every such issue is returned oldest first,
+/// each with its own `once_post_limit`.
+pub async fn get_rss_articles(
+    redis: Option<&redis_base::Redis>,
+    newsletter: &Newsletter,
+    source: &ArticleSourceConfig,
+) -> anyhow::Result<(Feed, Vec<WeeklyArticle>)> {
+    let mut once_post_limit = source.once_post_limit;
+    if once_post_limit == 0 {
+        once_post_limit = DEFAULT_ONCE_POST_LIMIT
+    }
+    let order = if source.catch_up {
+        PostOrder::OldestFirst
+    } else {
+        source.order
+    };
+    let dedup_group = source.dedup_group.as_deref();
+    info!(
+        "start fetching {} issues, once_post_limit: {}, url: {}",
+        newsletter.name, once_post_limit, newsletter.rss_url
+    );
+    let rss = Feed::try_new(&newsletter.rss_url).await?;
+    let link_marker = newsletter.link_marker();
+    let mut items = rss.channel.items.iter().collect::<Vec<_>>();
+    sort_by_date(&mut items, order, |v| &v.pub_date);
+
+    let client = redirect_client()?;
+    let mut articles = vec![];
+    for item in items {
+        let issue = parse_issue(&item.description, &link_marker);
+        let number = issue.number;
+        let mut arts: Vec<Article> = vec![];
+        for mut article in issue.into_articles() {
+            if arts.len() >= once_post_limit as usize {
+                break;
+            }
+            let Some(redis) = redis else {
+                arts.push(article);
+                continue;
+            };
+            if !redis.setnx(&newsletter.redis_key, &article.url) {
+                continue;
+            }
+            // Resolved once, a failure included, for both the card and the dedup key.
+            let resolved = if source.resolve_links || dedup_group.is_some() {
+                match resolve_redirect(&client, &article.url).await {
+                    Ok(v) => Some(v),
+                    Err(e) => {
+                        error!(
+                            "resolve {} link {} failed: {}",
+                            newsletter.name, article.url, e
+                        );
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+            if source.resolve_links {
+                if let Some(v) = &resolved {
+                    // Keep the real url next to the tracking one.
+                    redis.hset(&newsletter.redis_key, &article.url, v);
+                    article.resolved_url = Some(v.clone());
+                }
+            }
+            if let Some(g) = dedup_group {
+                let url = normalize_url(resolved.as_deref().unwrap_or(&article.url));
+                if !redis.setnx_group(g, &url) {
+                    info!("skip {}, it has been pushed in dedup group {}", url, g);
+                    continue;
+                }
+            }
+            arts.push(article);
+        }
+        let art_count = arts.len();
+        articles.push(WeeklyArticle {
+            date: item.pub_date.clone(),
+            issue: number,
+            articles: arts,
+        });
+        // Push just one week at once, unless catching up.
+        if art_count > 0 && !source.catch_up {
+            break;
+        }
+    }
+    Ok((rss, articles))
+}
+
+/// Builds the card header, e.g. `[Golang Weekly] #506 - 2024-05-07`.
+fn issue_title(title: &str, wa: &WeeklyArticle) -> String {
+    let date = match parse_date(&wa.date) {
+        Some(v) => v.format("%Y-%m-%d").to_string(),
+        None => wa.date.replace("00:00:00 +0000", "").trim().to_string(),
+    };
+    match wa.issue {
+        Some(v) => format!("[{}] #{} - {}", title, v, date),
+        None => format!("[{}] - {}", title, date),
+    }
+}
+
+async fn build_content(
+    name: &str,
+    articles: Vec<Article>,
+    openai_api_key: Option<String>,
+    openai_host: Option<String>,
+    proxy: Option<String>,
+) -> String {
+    let mut content = String::new();
+    for (i, article) in articles.iter().enumerate() {
+        // Group the articles under the heading of their section.
+        if !article.section.is_empty() && (i == 0 || articles[i - 1].section != article.section) {
+            content.push_str(format!("**{}**\n", article.section).as_str());
+        }
+        content.push_str(format!("{}", article).as_str());
+        if i != articles.len() - 1 {
+            content.push_str("---\n");
+        }
+    }
+    let c = build_feishu_content(
+        openai_api_key,
+        openai_host,
+        proxy,
+        build_req_content(name, content.clone()),
+    )
+    .await;
+    content.push_str(&c);
+    content
+}
+
+fn build_req_content(name: &str, content: String) -> String {
+    let mut res = String::with_capacity(content.len() + 128);
+    res.push_str(&format!("这是 {} 本周的重点文章\n", name));
+    res.push_str(&content);
+    res.push('\n');
+    res.push_str("请你使用中文每篇文章进行总结概括，不要超过100个字。\n");
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GO_WEEKLY_LINK_MARKER: &str = "https://golangweekly.com/link/";
+
+    #[test]
+    fn test_newsletter() {
+        let newsletter = Newsletter::new(
+            "Postgres Weekly",
+            "https://cprss.s3.amazonaws.com/postgresweekly.com.xml",
+            "postgresweekly.com",
+        );
+        assert_eq!(newsletter.link_marker(), "https://postgresweekly.com/link/");
+        assert_eq!(newsletter.home_url(), "https://postgresweekly.com/");
+        assert_eq!(
+            newsletter.redis_key,
+            "hedon-bot:newsletter-memory:postgres-weekly"
+        );
+    }
+
+    #[test]
+    fn test_parse_issue_with_other_link_domain() {
+        let data = include_str!("../../fixtures/item_description.xml")
+            .replace("golangweekly.com", "nodeweekly.com");
+        assert_eq!(
+            parse_issue(&data, "https://nodeweekly.com/link/").article_count(),
+            16
+        );
+        assert_eq!(parse_issue(&data, GO_WEEKLY_LINK_MARKER).article_count(), 0);
+    }
+
+    #[test]
+    fn test_resolve_item_description() {
+        let data = include_str!("../../fixtures/item_description.xml");
+        let articles = parse_issue(data, GO_WEEKLY_LINK_MARKER).into_articles();
+        assert_eq!(16, articles.len());
+        assert_eq!(
+            articles[0],
+            Article {
+                url: "https://golangweekly.com/link/154746/rss".to_string(),
+                title: "Evolving the Go Standard Library with <code>math/rand/v2</code>".to_string(),
+                description: "— Generating random numbers takes much more than you might think. Go’s initial RNG has multiple flaws, but fixing it breaks repeatability requirements. So, the core team created a “version 2” package that keeps Go’s compatibility promise and sets forth principles for future such 'version 2' packages generally.".to_string(),
+                author: "Russ Cox (The Go Team)".to_string(),
+                resolved_url: None,
+                section: "".to_string(),
+                sponsored: false,
+            }
+        );
+        assert_eq!(
+            articles[6],
+            Article {
+                url: "https://golangweekly.com/link/154763/rss".to_string(),
+                title: "Logdy: A Web-Based Viewer for Logs".to_string(),
+                description: "— Web based real-time log viewer. Stream any content to a web UI with autogenerated filters, then parse any format with TypeScript.".to_string(),
+                author: "Peter Osinski".to_string(),
+                resolved_url: None,
+                section: "🛠 Code & Tools".to_string(),
+                sponsored: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_issue() {
+        let data = include_str!("../../fixtures/item_description.xml");
+        let issue = parse_issue(data, GO_WEEKLY_LINK_MARKER);
+        assert_eq!(issue.number, Some(506));
+        let headings = issue
+            .sections
+            .iter()
+            .map(|v| (v.heading.as_str(), v.articles.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            headings,
+            vec![
+                ("", 1),
+                ("QUICK BITS", 2),
+                ("", 3),
+                ("🛠 Code & Tools", 2),
+                ("📰 Classifieds", 2),
+                ("🛠 Code & Tools", 6),
+            ]
+        );
+
+        let quick_bits = &issue.sections[1].articles[0];
+        assert_eq!(quick_bits.url, "https://golangweekly.com/link/154775/rss");
+        assert_eq!(quick_bits.title, "Go programming book 'Humble Bundle'");
+
+        let post = &issue.sections[2].articles[0];
+        assert_eq!(
+            post.title,
+            "Building a Highly-Available Search Engine using SQLite"
+        );
+        assert_eq!(post.description, "– Thanks to the Go-powered rqlite.");
+        assert_eq!(post.author, "Philip O'Toole");
+
+        let classified = &issue.sections[4].articles[0];
+        assert_eq!(classified.title, "Porkbun");
+        assert!(classified.sponsored);
+
+        let tool = &issue.sections[5].articles[0];
+        assert_eq!(tool.title, "gocron 2.5");
+        assert_eq!(
+            tool.description,
+            "– Run Go functions at pre-determined intervals."
+        );
+        assert!(!tool.sponsored);
+    }
+
+    #[test]
+    fn test_parse_issue_should_not_panic_on_unexpected_markup() {
+        for data in [
+            "",
+            "<table><td><p><span><a>no href</a></span></p></td></table>",
+            "<td><p><span><a href=\"https://golangweekly.com/link/1/rss\"></a></span></p></td>",
+            "<ul><li><a href=\"https://golangweekly.com/link/2/rss\">Only a link</a></li>",
+            "<table><tr><td>#abc</td></tr></table><p>unclosed",
+        ] {
+            let _ = parse_issue(data, GO_WEEKLY_LINK_MARKER);
+        }
+        let issue = parse_issue(
+            "<table><tr><td><ul><li><a href=\"https://golangweekly.com/link/2/rss\">Only a link</a></li></ul></td></tr></table>",
+            GO_WEEKLY_LINK_MARKER,
+        );
+        assert_eq!(issue.article_count(), 1);
+    }
+
+    #[test]
+    fn test_issue_title() {
+        let mut wa = WeeklyArticle {
+            date: "Tue, 07 May 2024 00:00:00 +0000".to_string(),
+            issue: Some(506),
+            articles: vec![],
+        };
+        assert_eq!(
+            issue_title("Golang Weekly", &wa),
+            "[Golang Weekly] #506 - 2024-05-07"
+        );
+        wa.issue = None;
+        wa.date = "May 7".to_string();
+        assert_eq!(issue_title("Golang Weekly", &wa), "[Golang Weekly] - May 7");
+    }
+
+    #[test]
+    fn test_display_article() {
+        let mut article = Article {
+            url: "https://golangweekly.com/link/154746/rss".to_string(),
+            title: "Evolving the Go Standard Library".to_string(),
+            description: "— A look at math/rand/v2.".to_string(),
+            author: "Russ Cox".to_string(),
+            resolved_url: None,
+            section: "".to_string(),
+            sponsored: false,
+        };
+        assert_eq!(
+            article.to_string(),
+            "**[Evolving the Go Standard Library](https://golangweekly.com/link/154746/rss)**: — A look at math/rand/v2. (_Russ Cox_)\n"
+        );
+        article.resolved_url = Some("https://go.dev/blog/randv2".to_string());
+        assert_eq!(
+            article.to_string(),
+            "**[Evolving the Go Standard Library](https://go.dev/blog/randv2)** (go.dev): — A look at math/rand/v2. (_Russ Cox_)\n"
+        );
+    }
+}
@@ -1,14 +1,14 @@
 use std::{thread, time::Duration};
 
-use serde_json::json;
 use tracing::{error, info};
 
 use crate::{
     chatgpt::build_feishu_content,
+    conf::ArticleSourceConfig,
     feeds::Feed,
-    feishu_bot,
-    redis_base::{self, Redis},
-    trim_str, DEFAULT_ONCE_POST_LIMIT,
+    notifier::{self, notify_all, Color, Message},
+    redis_base::{self, ItemState, Redis},
+    sort_by_date, trim_str, DEFAULT_ONCE_POST_LIMIT,
 };
 
 const REDIS_BLOG_RSS_URL: &str = "https://redis.io/blog/feed/";
@@ -26,21 +26,27 @@ pub struct Article {
 
 pub async fn send_feishu_msg(
     redis: &redis_base::Redis,
-    webhooks: Vec<String>,
-    once_post_limit: u8,
+    source: &ArticleSourceConfig,
     openai_api_key: Option<String>,
     openai_host: Option<String>,
     proxy: Option<String>,
 ) -> anyhow::Result<()> {
     info!("start fetching redis official blogs");
-    let (_, articles) = get_rss_articles(Some(redis), once_post_limit).await?;
+    let (_, articles) = get_rss_articles(Some(redis), source).await?;
     info!(
         "fetch redis official blogs success, articles count: {}",
         articles.len()
     );
-    let client = reqwest::Client::new();
-    for article in articles {
+    let notifiers = notifier::from_source(redis, "Redis Blog", source, None);
+    for (article, state) in articles {
         thread::sleep(Duration::from_secs(3));
+        if state == ItemState::Updated {
+            let msg = Message::updated(&article.title, &article.date, &article.url);
+            if let Err(e) = notify_all(&notifiers, &msg).await {
+                error!("{}", e);
+            }
+            continue;
+        }
         let content = build_content(
             &article,
             openai_api_key.clone(),
@@ -49,46 +55,15 @@ pub async fn send_feishu_msg(
         )
         .await;
         info!("build redis official blogs content success");
-        let req = &json!({
-                    "msg_type": "interactive",
-                    "card": {
-                        "elements": [
-                             {
-                                 "tag": "markdown",
-                                 "content": content,
-                             },
-                             {
-                                "actions": [{
-                                        "tag": "button",
-                                        "text": {
-                                                "content": "origin link",
-                                                "tag": "lark_md"
-                                        },
-                                        "url": format!("{}", article.url),
-                                        "type": "default",
-                                        "value": {}
-                                }],
-                                "tag": "action"
-                             }
-                        ],
-                        "header": {
-                                "title": {
-                                        "content": format!("{} ({}) \n           -- {}", article.title, article.author, article.date),
-                                        "tag": "plain_text"
-                                },
-                                "template": "red",
-                        }
-                }
-        });
-        for webhook in &webhooks {
-            let res: feishu_bot::SendMessageResp =
-                client.post(webhook).json(req).send().await?.json().await?;
-            if res.code != 0 {
-                error!(
-                    "send redis official blogs to feishu failed, code: {}, msg: {}",
-                    res.code, res.msg
-                );
-            }
+        let msg = Message::new(
+            format!("{} ({})", article.title, article.author),
+            Color::Red,
+        )
+        .date(article.date.as_str())
+        .markdown(content)
+        .button("origin link", article.url.as_str());
+        if let Err(e) = notify_all(&notifiers, &msg).await {
+            error!("{}", e);
         }
     }
     info!("finish fetching redis official blogs");
@@ -97,14 +72,15 @@ pub async fn send_feishu_msg(
 
 async fn get_rss_articles(
     redis: Option<&redis_base::Redis>,
-    mut once_post_limit: u8,
-) -> anyhow::Result<(Feed, Vec<Article>)> {
+    source: &ArticleSourceConfig,
+) -> anyhow::Result<(Feed, Vec<(Article, ItemState)>)> {
+    let mut once_post_limit = source.once_post_limit;
     if once_post_limit == 0 {
         once_post_limit = DEFAULT_ONCE_POST_LIMIT
     }
     let rss = Feed::try_new(REDIS_BLOG_RSS_URL).await?;
 
-    let articles: Vec<Article> = rss
+    let mut articles: Vec<Article> = rss
         .channel
         .items
         .iter()
@@ -116,21 +92,26 @@ async fn get_rss_articles(
             content: trim_str(&v.content),
             date: v.pub_date.to_string(),
         })
-        .filter(|v| {
-            if let Some(r) = redis {
-                r.setnx(Redis::HSET_REDIS_BLOG_KEY, &v.url)
-            } else {
-                true
-            }
-        })
-        .take_while(|_| {
-            if once_post_limit > 0 {
-                once_post_limit -= 1;
-                true
-            } else {
-                false
-            }
+        .collect();
+    sort_by_date(&mut articles, source.order, |v| &v.date);
+
+    let articles = articles
+        .into_iter()
+        .filter_map(|v| {
+            let state = match redis {
+                Some(r) => r.admit(
+                    Redis::HSET_REDIS_BLOG_KEY,
+                    &v.url,
+                    &v.date,
+                    &v.content,
+                    &v.url,
+                    source,
+                )?,
+                None => ItemState::New,
+            };
+            Some((v, state))
         })
+        .take(once_post_limit as usize)
         .collect();
 
     Ok((rss, articles))
@@ -172,7 +153,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_get_rss_articles() -> anyhow::Result<()> {
-        let (_, articles) = get_rss_articles(None, 0).await?;
+        let (_, articles) = get_rss_articles(None, &ArticleSourceConfig::default()).await?;
         println!("articles: {:?}", articles);
         Ok(())
     }
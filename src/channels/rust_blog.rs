@@ -1,35 +1,41 @@
 use std::{thread, time::Duration};
 
-use serde_json::json;
 use tracing::{error, info};
 
 use crate::{
     chatgpt::build_feishu_content,
+    conf::ArticleSourceConfig,
     feeds::{Atom, Entry},
-    feishu_bot,
-    redis_base::{self, Redis},
-    DEFAULT_ONCE_POST_LIMIT,
+    notifier::{self, notify_all, Color, Message},
+    redis_base::{self, ItemState, Redis},
+    sort_by_date, DEFAULT_ONCE_POST_LIMIT,
 };
 
 pub const RUST_BLOG_ATOM_URL: &str = "https://blog.rust-lang.org/feed.xml";
 
 pub async fn send_feishu_msg(
     redis: &redis_base::Redis,
-    webhooks: Vec<String>,
-    once_post_limit: u8,
+    source: &ArticleSourceConfig,
     openai_api_key: Option<String>,
     openai_host: Option<String>,
     proxy: Option<String>,
 ) -> anyhow::Result<()> {
     info!("start fetching rust official blogs");
-    let entries = get_atom_articles(Some(redis), once_post_limit, proxy.clone()).await?;
+    let entries = get_atom_articles(Some(redis), source, proxy.clone()).await?;
     info!(
         "fetch rust official blogs success, entries: {}",
         entries.len()
     );
-    let client = reqwest::Client::new();
-    for entry in entries {
+    let notifiers = notifier::from_source(redis, "Rust Blog", source, None);
+    for (entry, state) in entries {
         thread::sleep(Duration::from_secs(3));
+        if state == ItemState::Updated {
+            let msg = Message::updated(&entry.title, &entry.updated, &entry.link.href);
+            if let Err(e) = notify_all(&notifiers, &msg).await {
+                error!("{}", e);
+            }
+            continue;
+        }
         let content = build_content(
             &entry,
             openai_api_key.clone(),
@@ -38,46 +44,12 @@ pub async fn send_feishu_msg(
         )
         .await;
         info!("build rust official blogs content success");
-        let req = &json!({
-                    "msg_type": "interactive",
-                    "card": {
-                        "elements": [
-                             {
-                                 "tag": "markdown",
-                                 "content": content,
-                             },
-                             {
-                                "actions": [{
-                                        "tag": "button",
-                                        "text": {
-                                                "content": "origin link",
-                                                "tag": "lark_md"
-                                        },
-                                        "url": format!("{}", entry.link.href),
-                                        "type": "default",
-                                        "value": {}
-                                }],
-                                "tag": "action"
-                             }
-                        ],
-                        "header": {
-                                "title": {
-                                        "content": format!("{} \n           -- {}", entry.title,  entry.updated),
-                                        "tag": "plain_text"
-                                },
-                                "template": "orange",
-                        }
-                }
-        });
-        for webhook in &webhooks {
-            let res: feishu_bot::SendMessageResp =
-                client.post(webhook).json(req).send().await?.json().await?;
-            if res.code != 0 {
-                error!(
-                    "send rust official blogs to feishu failed, code: {}, msg: {}",
-                    res.code, res.msg
-                );
-            }
+        let msg = Message::new(entry.title.as_str(), Color::Orange)
+            .date(entry.updated.as_str())
+            .markdown(content)
+            .button("origin link", entry.link.href.as_str());
+        if let Err(e) = notify_all(&notifiers, &msg).await {
+            error!("{}", e);
         }
     }
     info!("finish fetching rust official blogs");
@@ -86,32 +58,35 @@ pub async fn send_feishu_msg(
 
 async fn get_atom_articles(
     redis: Option<&redis_base::Redis>,
-    mut once_post_limit: u8,
+    source: &ArticleSourceConfig,
     proxy: Option<String>,
-) -> anyhow::Result<Vec<Entry>> {
+) -> anyhow::Result<Vec<(Entry, ItemState)>> {
+    let mut once_post_limit = source.once_post_limit;
     if once_post_limit == 0 {
         once_post_limit = DEFAULT_ONCE_POST_LIMIT
     }
     let atom = Atom::try_new(RUST_BLOG_ATOM_URL, proxy).await?;
 
-    let entries = atom
-        .entry
+    let mut entries = atom.entry;
+    sort_by_date(&mut entries, source.order, |v| v.date());
+
+    let entries = entries
         .into_iter()
-        .filter(|v| {
-            if let Some(r) = redis {
-                r.setnx(Redis::HSET_RUST_BLOG_KEY, &v.id)
-            } else {
-                true
-            }
-        })
-        .take_while(|_| {
-            if once_post_limit > 0 {
-                once_post_limit -= 1;
-                true
-            } else {
-                false
-            }
+        .filter_map(|v| {
+            let state = match redis {
+                Some(r) => r.admit(
+                    Redis::HSET_RUST_BLOG_KEY,
+                    &v.id,
+                    &v.updated,
+                    &v.content,
+                    &v.link.href,
+                    source,
+                )?,
+                None => ItemState::New,
+            };
+            Some((v, state))
         })
+        .take(once_post_limit as usize)
         .collect::<Vec<_>>();
 
     Ok(entries)
@@ -0,0 +1,347 @@
+use std::{thread, time::Duration};
+
+use chrono::{NaiveDate, NaiveDateTime};
+use reqwest::{Client, Proxy};
+use scraper::{ElementRef, Html, Selector};
+use tracing::{error, info};
+use url::Url;
+
+use crate::{
+    chatgpt::build_feishu_content,
+    conf::{ScrapeConfig, ScrapeSelectors},
+    notifier::{self, notify_all, Color, Message},
+    redis_base::{self, ItemState, Redis},
+    sort_by_date, trim_str, DEFAULT_ONCE_POST_LIMIT,
+};
+
+#[derive(Debug, PartialEq)]
+pub struct Article {
+    pub url: String,
+    pub title: String,
+    pub description: String,
+    pub author: String,
+    pub content: String,
+    pub date: String,
+}
+
+pub async fn send_feishu_msg(
+    redis: &redis_base::Redis,
+    conf: &ScrapeConfig,
+    openai_api_key: Option<String>,
+    openai_host: Option<String>,
+    proxy: Option<String>,
+) -> anyhow::Result<()> {
+    info!("start scraping {}", conf.name);
+    let articles = get_articles(Some(redis), conf, proxy.clone()).await?;
+    info!(
+        "scrape {} success, articles count: {}",
+        conf.name,
+        articles.len()
+    );
+    let notifiers = notifier::from_source(redis, &conf.name, &conf.source, None);
+    for (article, state) in articles {
+        thread::sleep(Duration::from_secs(3));
+        if state == ItemState::Updated {
+            let msg = Message::updated(&article.title, &article.date, &article.url);
+            if let Err(e) = notify_all(&notifiers, &msg).await {
+                error!("{}", e);
+            }
+            continue;
+        }
+        let content = build_content(
+            &article,
+            openai_api_key.clone(),
+            openai_host.clone(),
+            proxy.clone(),
+        )
+        .await;
+        info!("build {} content success", conf.name);
+        let msg = Message::new(article.title.as_str(), Color::Turquoise)
+            .date(article.date.as_str())
+            .markdown(content)
+            .button("origin link", article.url.as_str());
+        if let Err(e) = notify_all(&notifiers, &msg).await {
+            error!("{}", e);
+        }
+    }
+    info!("finish scraping {}", conf.name);
+    Ok(())
+}
+
+async fn get_articles(
+    redis: Option<&redis_base::Redis>,
+    conf: &ScrapeConfig,
+    proxy: Option<String>,
+) -> anyhow::Result<Vec<(Article, ItemState)>> {
+    let mut once_post_limit = conf.source.once_post_limit;
+    if once_post_limit == 0 {
+        once_post_limit = DEFAULT_ONCE_POST_LIMIT
+    }
+    let html = send_request(&conf.url, proxy).await?;
+    let mut articles = resolve_html(
+        &html,
+        &conf.url,
+        &conf.selectors,
+        conf.date_format.as_deref(),
+    )?;
+    sort_by_date(&mut articles, conf.source.order, |v| &v.date);
+
+    let key = Redis::scrape_key(&conf.name);
+    let articles = articles
+        .into_iter()
+        .filter_map(|v| {
+            let state = match redis {
+                Some(r) => r.admit(&key, &v.url, &v.date, &v.description, &v.url, &conf.source)?,
+                None => ItemState::New,
+            };
+            Some((v, state))
+        })
+        .take(once_post_limit as usize)
+        .collect();
+    Ok(articles)
+}
+
+async fn send_request(url: &str, proxy: Option<String>) -> Result<String, reqwest::Error> {
+    info!(
+        "sending request to scrape {}, use proxy: {}",
+        url,
+        proxy.is_some()
+    );
+    let client: Client;
+    if let Some(proxy) = proxy {
+        let proxy = Proxy::https(proxy)?;
+        client = Client::builder().proxy(proxy).build()?;
+    } else {
+        client = reqwest::Client::new();
+    }
+    let resp = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    info!("scrape {} success, length: {}", url, resp.len());
+    Ok(resp)
+}
+
+/// Extracts the articles of a listing page with the configured selectors.
+///
+/// Items without a title or a link are skipped, relative links are resolved
+/// against `base_url`. Dates are converted to RFC 3339 when `date_format`
+/// is set, so they can be ordered like feed dates.
+fn resolve_html(
+    html: &str,
+    base_url: &str,
+    selectors: &ScrapeSelectors,
+    date_format: Option<&str>,
+) -> anyhow::Result<Vec<Article>> {
+    let item_selector = parse_selector(&selectors.item)?;
+    let title_selector = parse_selector(&selectors.title)?;
+    let link_selector = parse_selector(selectors.link.as_deref().unwrap_or("a"))?;
+    let date_selector = selectors.date.as_deref().map(parse_selector).transpose()?;
+    let summary_selector = selectors
+        .summary
+        .as_deref()
+        .map(parse_selector)
+        .transpose()?;
+    let author_selector = selectors
+        .author
+        .as_deref()
+        .map(parse_selector)
+        .transpose()?;
+    let content_selector = selectors
+        .content
+        .as_deref()
+        .map(parse_selector)
+        .transpose()?;
+    let base_url = Url::parse(base_url)?;
+
+    let document = Html::parse_document(html);
+    let mut res = vec![];
+    for item in document.select(&item_selector) {
+        let Some(title) = item.select(&title_selector).next().map(element_text) else {
+            continue;
+        };
+        let href = item
+            .select(&link_selector)
+            .find_map(|v| v.value().attr("href"));
+        let Some(url) = href.and_then(|v| base_url.join(v.trim()).ok()) else {
+            continue;
+        };
+        if title.is_empty() {
+            continue;
+        }
+        let date = date_selector
+            .as_ref()
+            .and_then(|s| item.select(s).next())
+            .map(|v| match v.value().attr("datetime") {
+                Some(d) => d.trim().to_string(),
+                None => element_text(v),
+            })
+            .map(|v| normalize_date(&v, date_format))
+            .unwrap_or_default();
+        let description = summary_selector
+            .as_ref()
+            .and_then(|s| item.select(s).next())
+            .map(element_text)
+            .unwrap_or_default();
+        let author = author_selector
+            .as_ref()
+            .and_then(|s| item.select(s).next())
+            .map(element_text)
+            .unwrap_or_default();
+        // only a separate content is summarised, the description is shown as it is
+        let content = content_selector
+            .as_ref()
+            .and_then(|s| item.select(s).next())
+            .map(element_text)
+            .unwrap_or_default();
+        res.push(Article {
+            url: url.to_string(),
+            title,
+            description,
+            author,
+            content,
+            date,
+        });
+    }
+    Ok(res)
+}
+
+fn parse_selector(selector: &str) -> anyhow::Result<Selector> {
+    Selector::parse(selector).map_err(|e| anyhow::anyhow!("invalid selector `{}`: {}", selector, e))
+}
+
+/// Converts `date` to RFC 3339 with `date_format`, or leaves it as it is.
+fn normalize_date(date: &str, date_format: Option<&str>) -> String {
+    let Some(format) = date_format else {
+        return date.to_string();
+    };
+    if let Ok(v) = NaiveDateTime::parse_from_str(date, format) {
+        return v.and_utc().to_rfc3339();
+    }
+    if let Ok(v) = NaiveDate::parse_from_str(date, format) {
+        if let Some(v) = v.and_hms_opt(0, 0, 0) {
+            return v.and_utc().to_rfc3339();
+        }
+    }
+    date.to_string()
+}
+
+fn element_text(element: ElementRef) -> String {
+    trim_str(&element.text().collect::<Vec<_>>().join(" "))
+}
+
+fn build_req_content(content: &str) -> String {
+    let mut res = String::with_capacity(content.len() + 128);
+    res.push_str("这是一篇文章的摘要：\n");
+    res.push_str(content);
+    res.push('\n');
+    res.push_str("请你使用中文对文章进行总结概括，不要超过150个字。\n");
+    res
+}
+
+async fn build_content(
+    article: &Article,
+    openai_api_key: Option<String>,
+    openai_host: Option<String>,
+    proxy: Option<String>,
+) -> String {
+    let mut content = String::with_capacity(4096);
+    if !article.author.is_empty() {
+        content.push_str(&format!("**Author**: {}\n", article.author));
+    }
+    content.push_str(&article.description);
+    if !article.content.is_empty() {
+        content.push_str(
+            &build_feishu_content(
+                openai_api_key.clone(),
+                openai_host.clone(),
+                proxy.clone(),
+                build_req_content(&article.content),
+            )
+            .await,
+        );
+    }
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn selectors() -> ScrapeSelectors {
+        ScrapeSelectors {
+            item: "article.post".to_string(),
+            title: ".post-title".to_string(),
+            link: None,
+            date: Some("time".to_string()),
+            summary: Some(".excerpt".to_string()),
+            author: Some(".author".to_string()),
+            content: None,
+        }
+    }
+
+    #[test]
+    fn resolve_html_should_work() -> anyhow::Result<()> {
+        let data = include_str!("../../fixtures/scrape_listing.html");
+        let articles = resolve_html(
+            data,
+            "https://blog.example.com/blog/",
+            &selectors(),
+            Some("%d/%m/%Y"),
+        )?;
+        assert_eq!(articles.len(), 3);
+        assert_eq!(
+            articles[0],
+            Article {
+                url: "https://blog.example.com/blog/zero-copy-parsing/".to_string(),
+                title: "Zero-copy parsing in practice".to_string(),
+                description: "How we removed allocations from our hot path.".to_string(),
+                author: "Jane Doe".to_string(),
+                content: "".to_string(),
+                date: "2024-07-29".to_string(),
+            }
+        );
+        assert_eq!(articles[1].date, "2024-07-01T00:00:00+00:00");
+        assert_eq!(articles[1].author, "");
+        assert_eq!(
+            articles[2].url,
+            "https://blog.example.com/blog/release-notes?utm_source=rss"
+        );
+        assert_eq!(articles[2].date, "");
+        Ok(())
+    }
+
+    #[test]
+    fn resolve_html_should_reject_invalid_selector() {
+        let mut selectors = selectors();
+        selectors.item = "article[".to_string();
+        assert!(resolve_html("", "https://blog.example.com/", &selectors, None).is_err());
+    }
+
+    #[test]
+    fn resolve_html_should_use_content_selector() -> anyhow::Result<()> {
+        let data = include_str!("../../fixtures/scrape_listing.html");
+        let mut selectors = selectors();
+        selectors.content = Some(".body".to_string());
+        let articles = resolve_html(data, "https://blog.example.com/blog/", &selectors, None)?;
+        assert_eq!(
+            articles[0].content,
+            "We profiled the parser and replaced owned strings with borrowed slices."
+        );
+        assert_eq!(articles[1].content, "");
+        Ok(())
+    }
+
+    #[test]
+    fn normalize_date_should_work() {
+        assert_eq!(
+            normalize_date("2024-07-29 08:30", Some("%Y-%m-%d %H:%M")),
+            "2024-07-29T08:30:00+00:00"
+        );
+        assert_eq!(normalize_date("July 29", Some("%Y-%m-%d")), "July 29");
+        assert_eq!(normalize_date("July 29", None), "July 29");
+    }
+}
@@ -0,0 +1,380 @@
+use std::{thread, time::Duration};
+
+use scraper::{ElementRef, Html, Node};
+use tracing::{error, info};
+
+use crate::{
+    conf::{PostOrder, TwirConfig},
+    feeds::{Atom, Entry},
+    notifier::{self, notify_all, Color, Message},
+    parse_date,
+    redis_base::{self, Redis},
+    sort_by_date, trim_str, DEFAULT_ONCE_POST_LIMIT,
+};
+
+pub const THIS_WEEK_IN_RUST_ATOM_URL: &str = "https://this-week-in-rust.org/atom.xml";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    pub title: String,
+    pub url: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Section {
+    pub heading: String,
+    pub items: Vec<Item>,
+}
+
+/// An issue reduced to the whitelisted sections.
+#[derive(Debug)]
+pub struct CuratedIssue {
+    pub entry: Entry,
+    pub sections: Vec<Section>,
+}
+
+pub async fn send_feishu_msg(
+    redis: &redis_base::Redis,
+    conf: &TwirConfig,
+    proxy: Option<String>,
+) -> anyhow::Result<()> {
+    info!("start fetching this week in rust");
+    let issues = get_atom_issues(Some(redis), conf, proxy).await?;
+    info!("fetch this week in rust success, issues: {}", issues.len());
+    let notifiers = notifier::from_source(redis, "This Week in Rust", &conf.source, None);
+    for (i, issue) in issues.iter().enumerate() {
+        if i > 0 {
+            thread::sleep(Duration::from_secs(3));
+        }
+        let msg = Message::new(issue_title(&issue.entry), Color::Orange)
+            .markdown(build_content(&issue.sections))
+            .button("Read the issue", issue.entry.link.href.as_str());
+        if let Err(e) = notify_all(&notifiers, &msg).await {
+            error!("{}", e);
+        }
+    }
+    info!("finish fetching this week in rust");
+    Ok(())
+}
+
+/// Fetches the unsent issues, only the first one unless `catch_up` is set.
+async fn get_atom_issues(
+    redis: Option<&redis_base::Redis>,
+    conf: &TwirConfig,
+    proxy: Option<String>,
+) -> anyhow::Result<Vec<CuratedIssue>> {
+    let atom = Atom::try_new(THIS_WEEK_IN_RUST_ATOM_URL, proxy).await?;
+    Ok(curate_issues(redis, conf, atom.entry))
+}
+
+fn curate_issues(
+    redis: Option<&redis_base::Redis>,
+    conf: &TwirConfig,
+    mut entries: Vec<Entry>,
+) -> Vec<CuratedIssue> {
+    let order = if conf.source.catch_up {
+        PostOrder::OldestFirst
+    } else {
+        conf.source.order
+    };
+    sort_by_date(&mut entries, order, |v| v.date());
+
+    let mut issues = vec![];
+    for entry in entries {
+        if let Some(r) = redis {
+            if !r.setnx(Redis::HSET_THIS_WEEK_IN_RUST_KEY, &entry.id) {
+                continue;
+            }
+        }
+        let html = if entry.content.is_empty() {
+            &entry.summary
+        } else {
+            &entry.content
+        };
+        let sections = curate_sections(parse_issue(html), conf);
+        if sections.is_empty() {
+            continue;
+        }
+        issues.push(CuratedIssue { entry, sections });
+        if !conf.source.catch_up {
+            break;
+        }
+    }
+    issues
+}
+
+/// Parses the html of an issue into its sections.
+///
+/// `<h2>` and `<h3>` start a section, its items are the top-level `<li>`
+/// led by a link. Sections without lists, such as "Crate of the Week",
+/// take their paragraphs with a link instead.
+pub fn parse_issue(html: &str) -> Vec<Section> {
+    let document = Html::parse_fragment(html);
+    let mut sections: Vec<Section> = vec![];
+    let mut section = Section::default();
+    let mut paragraphs = vec![];
+    for node in document.root_element().descendants() {
+        let Some(element) = ElementRef::wrap(node) else {
+            continue;
+        };
+        match element.value().name() {
+            "h2" | "h3" => {
+                push_section(&mut sections, section, &mut paragraphs);
+                section = Section {
+                    heading: element_text(element),
+                    items: vec![],
+                };
+            }
+            "li" if !has_ancestor(element, "li") => {
+                if let Some(item) = resolve_item(element) {
+                    section.items.push(item);
+                }
+            }
+            "p" if !has_ancestor(element, "li") => {
+                if let Some(item) = resolve_item(element) {
+                    paragraphs.push(item);
+                }
+            }
+            _ => {}
+        }
+    }
+    push_section(&mut sections, section, &mut paragraphs);
+    sections
+}
+
+/// Keeps the whitelisted sections in the order of the issue, each with its
+/// own limit. All sections are kept when no whitelist is configured.
+fn curate_sections(sections: Vec<Section>, conf: &TwirConfig) -> Vec<Section> {
+    let default_limit = match conf.source.once_post_limit {
+        0 => DEFAULT_ONCE_POST_LIMIT,
+        v => v,
+    };
+    sections
+        .into_iter()
+        .filter_map(|mut section| {
+            let limit = if conf.sections.is_empty() {
+                default_limit
+            } else {
+                let heading = section.heading.to_lowercase();
+                let filter = conf
+                    .sections
+                    .iter()
+                    .find(|v| heading.starts_with(&v.name.to_lowercase()))?;
+                filter.limit.unwrap_or(default_limit)
+            };
+            section.items.truncate(limit as usize);
+            Some(section)
+        })
+        .collect()
+}
+
+fn push_section(sections: &mut Vec<Section>, mut section: Section, paragraphs: &mut Vec<Item>) {
+    if section.items.is_empty() {
+        section.items.append(paragraphs);
+    }
+    paragraphs.clear();
+    if !section.items.is_empty() && !section.heading.is_empty() {
+        sections.push(section);
+    }
+}
+
+fn resolve_item(element: ElementRef) -> Option<Item> {
+    let a = element
+        .descendants()
+        .filter_map(ElementRef::wrap)
+        .find(|v| v.value().name() == "a" && v.value().attr("href").is_some())?;
+    let url = a.value().attr("href")?.trim();
+    if url.starts_with('#') {
+        return None;
+    }
+    let title = element_text(a);
+    let text = element_text(element);
+    let description = match text.strip_prefix(title.as_str()) {
+        Some(v) => v.trim(),
+        None => text.as_str(),
+    };
+    Some(Item {
+        url: url.to_string(),
+        description: description.to_string(),
+        title,
+    })
+}
+
+fn has_ancestor(element: ElementRef, name: &str) -> bool {
+    element.ancestors().any(|v| match v.value() {
+        Node::Element(e) => e.name() == name,
+        _ => false,
+    })
+}
+
+fn element_text(element: ElementRef) -> String {
+    trim_str(&element.text().collect::<String>())
+}
+
+fn issue_title(entry: &Entry) -> String {
+    match parse_date(entry.date()) {
+        Some(v) => format!("{} - {}", entry.title, v.format("%Y-%m-%d")),
+        None => entry.title.clone(),
+    }
+}
+
+fn build_content(sections: &[Section]) -> String {
+    let mut content = String::with_capacity(4096);
+    for (i, section) in sections.iter().enumerate() {
+        if i > 0 {
+            content.push_str("---\n");
+        }
+        content.push_str(&format!("**{}**\n", section.heading));
+        for item in &section.items {
+            if item.description.is_empty() {
+                content.push_str(&format!("- [{}]({})\n", item.title, item.url));
+            } else {
+                content.push_str(&format!(
+                    "- [{}]({}) {}\n",
+                    item.title, item.url, item.description
+                ));
+            }
+        }
+    }
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::conf::{ArticleSourceConfig, TwirSection};
+
+    use super::*;
+
+    fn entries() -> Vec<Entry> {
+        let data = include_str!("../../fixtures/this_week_in_rust.xml");
+        Atom::parse(data).unwrap().entry
+    }
+
+    fn conf(sections: Vec<TwirSection>, catch_up: bool) -> TwirConfig {
+        TwirConfig {
+            sections,
+            source: ArticleSourceConfig {
+                once_post_limit: 2,
+                catch_up,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_parse_issue() {
+        let entries = entries();
+        let sections = parse_issue(&entries[0].summary);
+        let headings = sections
+            .iter()
+            .map(|v| (v.heading.as_str(), v.items.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            headings,
+            vec![
+                ("Official", 2),
+                ("Newsletters", 1),
+                ("Project/Tooling Updates", 3),
+                ("Observations/Thoughts", 1),
+                ("Crate of the Week", 2),
+                ("CFP - Projects", 1),
+            ]
+        );
+        assert_eq!(
+            sections[0].items[0],
+            Item {
+                title: "crates.io: development update".to_string(),
+                url: "https://blog.rust-lang.org/2024/07/29/crates-io-development-update.html"
+                    .to_string(),
+                description: "".to_string(),
+            }
+        );
+        assert_eq!(sections[2].items[2].description, "brings new features");
+        assert_eq!(sections[4].items[0].title, "cargo-wizard");
+        assert_eq!(
+            sections[4].items[0].description,
+            "This week's crate is cargo-wizard, a cargo subcommand to apply performance-oriented profiles."
+        );
+    }
+
+    #[test]
+    fn test_curate_issues_with_whitelist() {
+        let conf = conf(
+            vec![
+                TwirSection {
+                    name: "crate of the week".to_string(),
+                    limit: Some(1),
+                },
+                TwirSection {
+                    name: "Project/Tooling".to_string(),
+                    limit: None,
+                },
+            ],
+            false,
+        );
+        // Issue 557 has no whitelisted section, so 558 is the first one to push.
+        let issues = curate_issues(None, &conf, entries());
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].entry.title, "This Week in Rust 558");
+        let headings = issues[0]
+            .sections
+            .iter()
+            .map(|v| (v.heading.as_str(), v.items.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            headings,
+            vec![("Project/Tooling Updates", 2), ("Crate of the Week", 1)]
+        );
+    }
+
+    #[test]
+    fn test_curate_issues_with_catch_up() {
+        let conf = conf(
+            vec![
+                TwirSection {
+                    name: "Official".to_string(),
+                    limit: None,
+                },
+                TwirSection {
+                    name: "Crate of the Week".to_string(),
+                    limit: Some(1),
+                },
+            ],
+            true,
+        );
+        let issues = curate_issues(None, &conf, entries());
+        let titles = issues
+            .iter()
+            .map(|v| v.entry.title.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            titles,
+            vec!["This Week in Rust 557", "This Week in Rust 558"]
+        );
+        let sections = &issues[1].sections;
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].items.len(), 2);
+        assert_eq!(sections[1].items.len(), 1);
+        assert_eq!(
+            issue_title(&issues[1].entry),
+            "This Week in Rust 558 - 2024-07-31"
+        );
+    }
+
+    #[test]
+    fn test_build_content() {
+        let content = build_content(&[Section {
+            heading: "Official".to_string(),
+            items: vec![Item {
+                title: "Announcing Rust 1.80.0".to_string(),
+                url: "https://blog.rust-lang.org/2024/07/25/Rust-1.80.0.html".to_string(),
+                description: "".to_string(),
+            }],
+        }]);
+        assert_eq!(
+            content,
+            "**Official**\n- [Announcing Rust 1.80.0](https://blog.rust-lang.org/2024/07/25/Rust-1.80.0.html)\n"
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use std::{
+    collections::BTreeMap,
     fs::{self},
     path::Path,
 };
@@ -11,24 +12,461 @@ pub struct Conf {
     pub openai_host: Option<String>,
     pub proxy: Option<String>,
     pub redis: RedisConf,
+    #[serde(default)]
+    pub feishu_app: Option<FeishuAppConfig>,
     pub go_weekly: ArticleSourceConfig,
     pub go_blog: ArticleSourceConfig,
     pub rust_blog: ArticleSourceConfig,
     pub rust_inside_blog: ArticleSourceConfig,
     pub redis_official_blog: ArticleSourceConfig,
+    #[serde(default)]
+    pub go_releases: Option<ArticleSourceConfig>,
+    #[serde(default)]
+    pub this_week_in_rust: Option<TwirConfig>,
+    #[serde(default)]
+    pub github_releases: Option<GithubReleasesConfig>,
+    #[serde(default)]
+    pub crates_io: Option<CratesIoConfig>,
+    #[serde(default)]
+    pub rustsec: Option<AdvisoryConfig>,
+    #[serde(default)]
+    pub go_vulns: Option<AdvisoryConfig>,
+    #[serde(default)]
+    pub newsletters: Vec<NewsletterConfig>,
+    #[serde(default)]
+    pub scrapers: Vec<ScrapeConfig>,
+    #[serde(default)]
+    pub media_feeds: Vec<MediaFeedConfig>,
+    #[serde(default)]
+    pub mailboxes: Vec<MailboxConfig>,
+    #[serde(default)]
+    pub email_digest: Option<EmailDigestConfig>,
 }
 
+/// Credentials of a Feishu app, webhook robots cannot upload the images shown in cards.
 #[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct FeishuAppConfig {
+    pub app_id: String,
+    pub app_secret: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
 pub struct ArticleSourceConfig {
     // sec   min   hour   day of month   month   day of week   year
     // *     *     *      *              *       *             *
     pub cron_expression: String,
 
-    // webhoos of Feishu robots.
-    pub webhooks: Vec<String>,
+    // webhoos of Feishu robots, a url or a `url` with the `secret` of the
+    // signature verification of the robot.
+    #[serde(default)]
+    pub webhooks: Vec<FeishuWebhookConfig>,
+
+    // incoming webhooks of Slack apps.
+    #[serde(default)]
+    pub slack_webhooks: Vec<String>,
+
+    // webhooks of DingTalk robots, with the secret of their signature setting.
+    #[serde(default)]
+    pub dingtalk_webhooks: Vec<DingTalkWebhookConfig>,
+
+    // webhooks of WeCom (企业微信) robots.
+    #[serde(default)]
+    pub wecom_webhooks: Vec<String>,
+
+    // Telegram chats, each with the token of the bot posting to it.
+    #[serde(default)]
+    pub telegram_chats: Vec<TelegramChatConfig>,
+
+    // webhooks of Discord channels.
+    #[serde(default)]
+    pub discord_webhooks: Vec<String>,
+
+    // add the pushed messages to the email digest, default is `false`
+    #[serde(default)]
+    pub email_digest: bool,
+
+    // arbitrary HTTP endpoints, with the template of the JSON body they receive
+    #[serde(default)]
+    pub http_webhooks: Vec<HttpWebhookConfig>,
 
     // a limit on the number of articles it can push at a time, default is `5`
     pub once_post_limit: u8,
+
+    // the order in which unsent articles are pushed, default is `oldest_first`
+    #[serde(default)]
+    pub order: PostOrder,
+
+    // push a compact "article updated" card when a pushed article changes, default is `false`
+    #[serde(default)]
+    pub notify_updates: bool,
+
+    // channels sharing a dedup group push the same article only once, by canonical url
+    #[serde(default)]
+    pub dedup_group: Option<String>,
+
+    // follow newsletter tracking links to the real article url, only used by newsletters, default is `false`
+    #[serde(default)]
+    pub resolve_links: bool,
+
+    // push every issue with unsent articles as its own card, oldest first, only used by newsletters, default is `false`
+    #[serde(default)]
+    pub catch_up: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, PartialEq)]
+#[serde(from = "FeishuWebhookRepr")]
+pub struct FeishuWebhookConfig {
+    pub url: String,
+    // requests are signed with it when set
+    pub secret: Option<String>,
+}
+
+/// A webhook is configured as a bare url when it has no secret.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FeishuWebhookRepr {
+    Url(String),
+    Signed {
+        url: String,
+        #[serde(default)]
+        secret: Option<String>,
+    },
+}
+
+impl From<FeishuWebhookRepr> for FeishuWebhookConfig {
+    fn from(v: FeishuWebhookRepr) -> Self {
+        match v {
+            FeishuWebhookRepr::Url(url) => FeishuWebhookConfig { url, secret: None },
+            FeishuWebhookRepr::Signed { url, secret } => FeishuWebhookConfig { url, secret },
+        }
+    }
+}
+
+impl From<&str> for FeishuWebhookConfig {
+    fn from(url: &str) -> Self {
+        FeishuWebhookConfig {
+            url: url.to_string(),
+            secret: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct DingTalkWebhookConfig {
+    pub url: String,
+    // the `SEC...` secret of a robot using the signature security setting
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct HttpWebhookConfig {
+    pub url: String,
+
+    // a Handlebars template of the JSON body, over the fields `channel`, `title`,
+    // `date`, `color`, `content`, `url`, `buttons` and `images`
+    pub template: String,
+
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+
+    // the statuses of a successful request, default is any `2xx`
+    #[serde(default)]
+    pub expected_status: Vec<u16>,
+
+    // the number of retries of a failed request, with an exponential backoff, default is `2`, at most `5`
+    #[serde(default = "default_http_webhook_retries")]
+    pub retries: u32,
+}
+
+fn default_http_webhook_retries() -> u32 {
+    2
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct TelegramChatConfig {
+    pub bot_token: String,
+    // the id of the chat, or `@username` for a public channel
+    pub chat_id: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PostOrder {
+    // push the oldest unsent articles first, so a backlog drains chronologically.
+    #[default]
+    OldestFirst,
+    // push the newest unsent articles first.
+    NewestFirst,
+    // keep the order in which the feed lists its items.
+    FeedOrder,
+}
+
+/// This Week in Rust, `once_post_limit` is the default limit of each section.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct TwirConfig {
+    // the sections to push, matched by prefix and case-insensitively, all sections if empty
+    #[serde(default)]
+    pub sections: Vec<TwirSection>,
+
+    #[serde(flatten)]
+    pub source: ArticleSourceConfig,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct TwirSection {
+    pub name: String,
+    // a limit on the number of items of the section, default is `once_post_limit`
+    #[serde(default)]
+    pub limit: Option<u8>,
+}
+
+/// GitHub releases of several repositories, `once_post_limit` applies to each of them.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct GithubReleasesConfig {
+    // push pre-releases as well, default is `false`
+    #[serde(default)]
+    pub include_prereleases: bool,
+
+    pub repos: Vec<GithubRepoConfig>,
+
+    #[serde(flatten)]
+    pub source: ArticleSourceConfig,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct GithubRepoConfig {
+    // `owner/repo`, e.g. `tokio-rs/tokio`
+    pub repo: String,
+
+    // a semver requirement the released version must match, e.g. `>=1.38`
+    #[serde(default)]
+    pub version: Option<String>,
+
+    // only keep the tags starting with it, e.g. `tokio-` in a repository releasing several crates
+    #[serde(default)]
+    pub tag_prefix: Option<String>,
+
+    // overrides `include_prereleases` of the channel
+    #[serde(default)]
+    pub include_prereleases: Option<bool>,
+}
+
+/// New versions of the watched crates on crates.io, `once_post_limit` applies to each of them.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct CratesIoConfig {
+    // push pre-release versions as well, default is `false`
+    #[serde(default)]
+    pub include_prereleases: bool,
+
+    pub crates: Vec<WatchedCrateConfig>,
+
+    #[serde(flatten)]
+    pub source: ArticleSourceConfig,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct WatchedCrateConfig {
+    pub name: String,
+
+    // link of the changelog, the repository of the crate is linked if not set
+    #[serde(default)]
+    pub changelog: Option<String>,
+}
+
+/// Security advisories of an OSV database for the packages we depend on.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct AdvisoryConfig {
+    // crates or Go modules to watch, e.g. `tokio` or `golang.org/x/net`, `stdlib` for the Go standard library
+    pub packages: Vec<String>,
+
+    // a local directory of OSV files, e.g. the `crates` directory of a checkout of the `osv` branch of
+    // `https://github.com/rustsec/advisory-db`, `api.osv.dev` is queried if not set
+    #[serde(default)]
+    pub local_path: Option<String>,
+
+    #[serde(flatten)]
+    pub source: ArticleSourceConfig,
+}
+
+/// A YouTube channel feed or a podcast feed.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct MediaFeedConfig {
+    pub name: String,
+
+    // e.g. `https://www.youtube.com/feeds/videos.xml?channel_id=<id>`
+    pub url: String,
+
+    #[serde(flatten)]
+    pub source: ArticleSourceConfig,
+}
+
+/// Email-only newsletters, read from one of a Maildir, an mbox file or an IMAP mailbox.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct MailboxConfig {
+    pub name: String,
+
+    // path of a Maildir, with `cur` and `new` directories
+    #[serde(default)]
+    pub maildir: Option<String>,
+
+    // path of an mbox file
+    #[serde(default)]
+    pub mbox: Option<String>,
+
+    #[serde(default)]
+    pub imap: Option<ImapConfig>,
+
+    // only push the messages whose sender contains it, e.g. `hello@changelog.com`
+    #[serde(default)]
+    pub from: Option<String>,
+
+    #[serde(flatten)]
+    pub source: ArticleSourceConfig,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct ImapConfig {
+    pub host: String,
+
+    // default is `993`
+    #[serde(default = "default_imap_port")]
+    pub port: u16,
+
+    pub username: String,
+    pub password: String,
+
+    // default is `INBOX`
+    #[serde(default = "default_imap_mailbox")]
+    pub mailbox: String,
+
+    // default is `true`
+    #[serde(default = "default_imap_tls")]
+    pub tls: bool,
+}
+
+fn default_imap_port() -> u16 {
+    993
+}
+
+fn default_imap_mailbox() -> String {
+    "INBOX".to_string()
+}
+
+fn default_imap_tls() -> bool {
+    true
+}
+
+/// A periodic email of the messages pushed since the previous one by the
+/// channels setting `email_digest`.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct EmailDigestConfig {
+    // when the digest is sent, e.g. once a day
+    pub cron_expression: String,
+
+    pub smtp: SmtpConfig,
+
+    // the sender, e.g. `Hedon Bot <hedon-bot@example.com>`
+    pub from: String,
+
+    pub recipients: Vec<String>,
+
+    // default is `Hedon Bot digest`
+    #[serde(default = "default_digest_subject")]
+    pub subject: String,
+}
+
+fn default_digest_subject() -> String {
+    "Hedon Bot digest".to_string()
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct SmtpConfig {
+    pub host: String,
+
+    // default is `587`
+    #[serde(default = "default_smtp_port")]
+    pub port: u16,
+
+    // no authentication when empty
+    #[serde(default)]
+    pub username: String,
+    #[serde(default)]
+    pub password: String,
+
+    // default is `starttls`
+    #[serde(default)]
+    pub tls: SmtpTls,
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpTls {
+    // plain text, e.g. for a local relay.
+    None,
+    // upgrade the connection with STARTTLS, usually on port 587.
+    #[default]
+    #[serde(rename = "starttls")]
+    StartTls,
+    // implicit TLS, usually on port 465.
+    Tls,
+}
+
+/// A newsletter on the Cooperpress template, e.g. Postgres Weekly or Node Weekly.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct NewsletterConfig {
+    pub name: String,
+
+    // the rss feed of the newsletter, e.g. `https://cprss.s3.amazonaws.com/postgresweekly.com.xml`
+    pub rss_url: String,
+
+    // the domain of the article links in the issues, e.g. `postgresweekly.com`
+    pub link_domain: String,
+
+    #[serde(flatten)]
+    pub source: ArticleSourceConfig,
+}
+
+/// A site without feeds, whose listing page is scraped with CSS selectors.
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct ScrapeConfig {
+    pub name: String,
+
+    // the listing page of the articles
+    pub url: String,
+
+    pub selectors: ScrapeSelectors,
+
+    // the chrono format of the scraped dates, e.g. `%B %d, %Y`, they are kept as they are if not set
+    #[serde(default)]
+    pub date_format: Option<String>,
+
+    #[serde(flatten)]
+    pub source: ArticleSourceConfig,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct ScrapeSelectors {
+    // matches each article on the listing page, the other selectors are relative to it
+    pub item: String,
+    pub title: String,
+    // the element holding the `href`, default is `a`
+    #[serde(default)]
+    pub link: Option<String>,
+    // the element holding the date, its `datetime` attribute is preferred to its text
+    #[serde(default)]
+    pub date: Option<String>,
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub author: Option<String>,
+    // the text summarised by OpenAI, nothing is summarised without it
+    #[serde(default)]
+    pub content: Option<String>,
 }
 
 #[derive(Debug, Deserialize, PartialEq)]
@@ -43,14 +481,37 @@ impl Conf {
     pub fn load<P: AsRef<Path>>(p: P) -> anyhow::Result<Conf> {
         let f = fs::read_to_string(p)?;
         let conf: Conf = serde_yml::from_str(&f)?;
+        conf.validate()?;
         Ok(conf)
     }
+
+    /// Rejects the settings that parse but cannot work.
+    fn validate(&self) -> anyhow::Result<()> {
+        if let Some(digest) = &self.email_digest {
+            // the queued messages would never be sent nor drained
+            if digest.recipients.is_empty() {
+                anyhow::bail!("email_digest needs at least one recipient");
+            }
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_validate_email_digest_recipients() {
+        let mut conf = Conf::load("./config.template.yml").unwrap();
+        assert!(conf.validate().is_ok());
+        conf.email_digest.as_mut().unwrap().recipients.clear();
+        assert_eq!(
+            conf.validate().unwrap_err().to_string(),
+            "email_digest needs at least one recipient"
+        );
+    }
+
     #[test]
     fn test_load_conf() {
         let conf = Conf::load("./config.template.yml");
@@ -68,46 +529,348 @@ mod tests {
                     host: "localhost".to_string(),
                     port: 6379,
                 },
+                feishu_app: Some(FeishuAppConfig {
+                    app_id: "cli_xxx".to_string(),
+                    app_secret: "xxx".to_string(),
+                }),
                 go_weekly: ArticleSourceConfig {
                     cron_expression: "0 30 10 * * * *".to_string(),
                     webhooks: vec![
-                        "http://example.com/webhook1".to_string(),
-                        "http://example.com/webhook2".to_string()
+                        "http://example.com/webhook1".into(),
+                        FeishuWebhookConfig {
+                            url: "http://example.com/webhook2".to_string(),
+                            secret: Some("xxx".to_string()),
+                        },
                     ],
+                    slack_webhooks: vec![],
+                    dingtalk_webhooks: vec![],
+                    wecom_webhooks: vec![],
+                    telegram_chats: vec![],
+                    discord_webhooks: vec![],
+                    email_digest: false,
+                    http_webhooks: vec![],
                     once_post_limit: 5,
+                    order: PostOrder::OldestFirst,
+                    notify_updates: false,
+                    dedup_group: Some("go".to_string()),
+                    resolve_links: true,
+                    catch_up: true,
                 },
                 go_blog: ArticleSourceConfig {
                     cron_expression: "0 30 10 * * * *".to_string(),
                     webhooks: vec![
-                        "http://example.com/webhook1".to_string(),
-                        "http://example.com/webhook2".to_string()
+                        "http://example.com/webhook1".into(),
+                        "http://example.com/webhook2".into()
                     ],
+                    slack_webhooks: vec![],
+                    dingtalk_webhooks: vec![],
+                    wecom_webhooks: vec![],
+                    telegram_chats: vec![],
+                    discord_webhooks: vec![],
+                    email_digest: false,
+                    http_webhooks: vec![HttpWebhookConfig {
+                        url: "https://internal.example.com/api/articles".to_string(),
+                        template: r#"{"source": "{{channel}}", "title": "{{title}}", "link": "{{url}}", "summary": "{{content}}"}"#
+                            .to_string(),
+                        headers: BTreeMap::from([(
+                            "Authorization".to_string(),
+                            "Bearer xxx".to_string()
+                        )]),
+                        expected_status: vec![200, 201],
+                        retries: 3,
+                    }],
                     once_post_limit: 1,
+                    order: PostOrder::OldestFirst,
+                    notify_updates: false,
+                    dedup_group: Some("go".to_string()),
+                    resolve_links: false,
+                    catch_up: false,
                 },
                 rust_blog: ArticleSourceConfig {
                     cron_expression: "0 30 10 * * * *".to_string(),
                     webhooks: vec![
-                        "http://example.com/webhook1".to_string(),
-                        "http://example.com/webhook2".to_string()
+                        "http://example.com/webhook1".into(),
+                        "http://example.com/webhook2".into()
                     ],
+                    slack_webhooks: vec!["https://hooks.slack.com/services/T000/B000/XXXX".to_string()],
+                    dingtalk_webhooks: vec![],
+                    wecom_webhooks: vec![],
+                    telegram_chats: vec![],
+                    discord_webhooks: vec![],
+                    email_digest: false,
+                    http_webhooks: vec![],
                     once_post_limit: 2,
+                    order: PostOrder::NewestFirst,
+                    notify_updates: true,
+                    dedup_group: None,
+                    resolve_links: false,
+                    catch_up: false,
                 },
                 rust_inside_blog: ArticleSourceConfig {
                     cron_expression: "0 30 10 * * * *".to_string(),
                     webhooks: vec![
-                        "http://example.com/webhook1".to_string(),
-                        "http://example.com/webhook2".to_string()
+                        "http://example.com/webhook1".into(),
+                        "http://example.com/webhook2".into()
+                    ],
+                    slack_webhooks: vec![],
+                    dingtalk_webhooks: vec![
+                        DingTalkWebhookConfig {
+                            url: "https://oapi.dingtalk.com/robot/send?access_token=xxx"
+                                .to_string(),
+                            secret: Some("SECxxx".to_string()),
+                        },
+                        DingTalkWebhookConfig {
+                            url: "https://oapi.dingtalk.com/robot/send?access_token=yyy"
+                                .to_string(),
+                            secret: None,
+                        },
+                    ],
+                    wecom_webhooks: vec![
+                        "https://qyapi.weixin.qq.com/cgi-bin/webhook/send?key=xxx".to_string()
                     ],
+                    telegram_chats: vec![],
+                    discord_webhooks: vec![],
+                    email_digest: false,
+                    http_webhooks: vec![],
                     once_post_limit: 3,
+                    order: PostOrder::OldestFirst,
+                    notify_updates: false,
+                    dedup_group: None,
+                    resolve_links: false,
+                    catch_up: false,
                 },
                 redis_official_blog: ArticleSourceConfig {
                     cron_expression: "0 30 10 * * * *".to_string(),
                     webhooks: vec![
-                        "http://example.com/webhook1".to_string(),
-                        "http://example.com/webhook2".to_string()
+                        "http://example.com/webhook1".into(),
+                        "http://example.com/webhook2".into()
                     ],
+                    slack_webhooks: vec![],
+                    dingtalk_webhooks: vec![],
+                    wecom_webhooks: vec![],
+                    telegram_chats: vec![TelegramChatConfig {
+                        bot_token: "123456:ABC-DEF".to_string(),
+                        chat_id: "@hedon_bot_news".to_string(),
+                    }],
+                    discord_webhooks: vec![
+                        "https://discord.com/api/webhooks/123/xxx".to_string()
+                    ],
+                    email_digest: false,
+                    http_webhooks: vec![],
                     once_post_limit: 1,
-                }
+                    order: PostOrder::FeedOrder,
+                    notify_updates: false,
+                    dedup_group: None,
+                    resolve_links: false,
+                    catch_up: false,
+                },
+                go_releases: Some(ArticleSourceConfig {
+                    cron_expression: "0 0 */2 * * * *".to_string(),
+                    webhooks: vec!["http://example.com/webhook1".into()],
+                    once_post_limit: 2,
+                    ..Default::default()
+                }),
+                this_week_in_rust: Some(TwirConfig {
+                    sections: vec![
+                        TwirSection {
+                            name: "Official".to_string(),
+                            limit: None,
+                        },
+                        TwirSection {
+                            name: "Project/Tooling Updates".to_string(),
+                            limit: Some(3),
+                        },
+                        TwirSection {
+                            name: "Crate of the Week".to_string(),
+                            limit: Some(1),
+                        },
+                    ],
+                    source: ArticleSourceConfig {
+                        cron_expression: "0 0 10 * * Thu *".to_string(),
+                        webhooks: vec!["http://example.com/webhook1".into()],
+                        once_post_limit: 5,
+                        ..Default::default()
+                    },
+                }),
+                github_releases: Some(GithubReleasesConfig {
+                    include_prereleases: false,
+                    repos: vec![
+                        GithubRepoConfig {
+                            repo: "tokio-rs/tokio".to_string(),
+                            version: Some(">=1.38".to_string()),
+                            tag_prefix: Some("tokio-".to_string()),
+                            include_prereleases: None,
+                        },
+                        GithubRepoConfig {
+                            repo: "redis-rs/redis-rs".to_string(),
+                            version: None,
+                            tag_prefix: None,
+                            include_prereleases: Some(true),
+                        },
+                    ],
+                    source: ArticleSourceConfig {
+                        cron_expression: "0 0 9 * * * *".to_string(),
+                        webhooks: vec!["http://example.com/webhook1".into()],
+                        once_post_limit: 3,
+                        ..Default::default()
+                    },
+                }),
+                crates_io: Some(CratesIoConfig {
+                    include_prereleases: false,
+                    crates: vec![
+                        WatchedCrateConfig {
+                            name: "tokio".to_string(),
+                            changelog: Some(
+                                "https://github.com/tokio-rs/tokio/blob/master/tokio/CHANGELOG.md"
+                                    .to_string()
+                            ),
+                        },
+                        WatchedCrateConfig {
+                            name: "serde".to_string(),
+                            changelog: None,
+                        },
+                    ],
+                    source: ArticleSourceConfig {
+                        cron_expression: "0 30 9 * * * *".to_string(),
+                        webhooks: vec!["http://example.com/webhook1".into()],
+                        once_post_limit: 3,
+                        ..Default::default()
+                    },
+                }),
+                rustsec: Some(AdvisoryConfig {
+                    packages: vec!["tokio".to_string(), "hyper".to_string()],
+                    local_path: None,
+                    source: ArticleSourceConfig {
+                        cron_expression: "0 */30 * * * * *".to_string(),
+                        webhooks: vec!["http://example.com/webhook1".into()],
+                        once_post_limit: 5,
+                        notify_updates: true,
+                        ..Default::default()
+                    },
+                }),
+                go_vulns: Some(AdvisoryConfig {
+                    packages: vec!["stdlib".to_string(), "golang.org/x/net".to_string()],
+                    local_path: None,
+                    source: ArticleSourceConfig {
+                        cron_expression: "0 */30 * * * * *".to_string(),
+                        webhooks: vec!["http://example.com/webhook1".into()],
+                        once_post_limit: 5,
+                        ..Default::default()
+                    },
+                }),
+                newsletters: vec![NewsletterConfig {
+                    name: "Postgres Weekly".to_string(),
+                    rss_url: "https://cprss.s3.amazonaws.com/postgresweekly.com.xml".to_string(),
+                    link_domain: "postgresweekly.com".to_string(),
+                    source: ArticleSourceConfig {
+                        cron_expression: "0 0 11 * * * *".to_string(),
+                        webhooks: vec!["http://example.com/webhook1".into()],
+                        slack_webhooks: vec![],
+                        dingtalk_webhooks: vec![],
+                        wecom_webhooks: vec![],
+                        telegram_chats: vec![],
+                        discord_webhooks: vec![],
+                        email_digest: true,
+                        http_webhooks: vec![],
+                        once_post_limit: 5,
+                        order: PostOrder::OldestFirst,
+                        notify_updates: false,
+                        dedup_group: None,
+                        resolve_links: true,
+                        catch_up: false,
+                    },
+                }],
+                scrapers: vec![ScrapeConfig {
+                    name: "Example Engineering".to_string(),
+                    url: "https://blog.example.com/".to_string(),
+                    selectors: ScrapeSelectors {
+                        item: "article.post".to_string(),
+                        title: ".post-title".to_string(),
+                        link: Some(".post-title a".to_string()),
+                        date: Some("time".to_string()),
+                        summary: Some(".excerpt".to_string()),
+                        author: Some(".author".to_string()),
+                        content: None,
+                    },
+                    date_format: Some("%B %d, %Y".to_string()),
+                    source: ArticleSourceConfig {
+                        cron_expression: "0 0 12 * * * *".to_string(),
+                        webhooks: vec!["http://example.com/webhook1".into()],
+                        once_post_limit: 3,
+                        ..Default::default()
+                    },
+                }],
+                media_feeds: vec![
+                    MediaFeedConfig {
+                        name: "RustConf".to_string(),
+                        url: "https://www.youtube.com/feeds/videos.xml?channel_id=UCaYhcUwRBNscFNUKTjgPFiA"
+                            .to_string(),
+                        source: ArticleSourceConfig {
+                            cron_expression: "0 0 13 * * * *".to_string(),
+                            webhooks: vec!["http://example.com/webhook1".into()],
+                            once_post_limit: 3,
+                            ..Default::default()
+                        },
+                    },
+                    MediaFeedConfig {
+                        name: "Go Time".to_string(),
+                        url: "https://changelog.com/gotime/feed".to_string(),
+                        source: ArticleSourceConfig {
+                            cron_expression: "0 0 13 * * * *".to_string(),
+                            webhooks: vec!["http://example.com/webhook1".into()],
+                            once_post_limit: 1,
+                            ..Default::default()
+                        },
+                    },
+                ],
+                mailboxes: vec![
+                    MailboxConfig {
+                        name: "Changelog News".to_string(),
+                        maildir: Some("/var/mail/hedon-bot".to_string()),
+                        mbox: None,
+                        imap: None,
+                        from: Some("hello@changelog.com".to_string()),
+                        source: ArticleSourceConfig {
+                            cron_expression: "0 0 14 * * * *".to_string(),
+                            webhooks: vec!["http://example.com/webhook1".into()],
+                            once_post_limit: 1,
+                            ..Default::default()
+                        },
+                    },
+                    MailboxConfig {
+                        name: "Rust Weekly".to_string(),
+                        maildir: None,
+                        mbox: None,
+                        imap: Some(ImapConfig {
+                            host: "imap.example.com".to_string(),
+                            port: 993,
+                            username: "bot@example.com".to_string(),
+                            password: "password123".to_string(),
+                            mailbox: "Newsletters".to_string(),
+                            tls: true,
+                        }),
+                        from: None,
+                        source: ArticleSourceConfig {
+                            cron_expression: "0 0 14 * * * *".to_string(),
+                            webhooks: vec!["http://example.com/webhook1".into()],
+                            once_post_limit: 1,
+                            ..Default::default()
+                        },
+                    },
+                ],
+                email_digest: Some(EmailDigestConfig {
+                    cron_expression: "0 0 9 * * * *".to_string(),
+                    smtp: SmtpConfig {
+                        host: "smtp.example.com".to_string(),
+                        port: 587,
+                        username: "hedon-bot@example.com".to_string(),
+                        password: "password123".to_string(),
+                        tls: SmtpTls::StartTls,
+                    },
+                    from: "Hedon Bot <hedon-bot@example.com>".to_string(),
+                    recipients: vec!["dev@example.com".to_string()],
+                    subject: "Hedon Bot daily digest".to_string(),
+                }),
             }
         )
     }
@@ -4,8 +4,14 @@ use std::sync::Arc;
 use tracing::{error, info};
 
 use crate::{
-    channels::{go_blog, go_weekly, redis_blog, rust_blog, rust_inside_blog},
+    channels::{
+        advisories::{self, Database},
+        crates_io, github_releases, go_blog, go_releases, go_weekly, mailbox, media,
+        newsletter::{self, Newsletter},
+        redis_blog, rust_blog, rust_inside_blog, scrape, this_week_in_rust,
+    },
     conf::Conf,
+    notifier::email,
     redis_base::Redis,
 };
 
@@ -21,8 +27,7 @@ async fn run_jobs(redis: Arc<Redis>, conf: Arc<Conf>) -> anyhow::Result<()> {
         async move {
             match go_weekly::send_feishu_msg(
                 redis.as_ref(),
-                conf.go_weekly.webhooks.clone(),
-                conf.go_weekly.once_post_limit,
+                &conf.go_weekly,
                 conf.openai_api_key.clone(),
                 conf.openai_host.clone(),
                 conf.proxy.clone(),
@@ -45,8 +50,7 @@ async fn run_jobs(redis: Arc<Redis>, conf: Arc<Conf>) -> anyhow::Result<()> {
         async move {
             match redis_blog::send_feishu_msg(
                 redis.as_ref(),
-                conf.redis_official_blog.webhooks.clone(),
-                conf.redis_official_blog.once_post_limit,
+                &conf.redis_official_blog,
                 conf.openai_api_key.clone(),
                 conf.openai_host.clone(),
                 conf.proxy.clone(),
@@ -69,8 +73,7 @@ async fn run_jobs(redis: Arc<Redis>, conf: Arc<Conf>) -> anyhow::Result<()> {
         async move {
             match go_blog::send_feishu_msg(
                 redis.as_ref(),
-                conf.go_blog.webhooks.clone(),
-                conf.go_blog.once_post_limit,
+                &conf.go_blog,
                 conf.openai_api_key.clone(),
                 conf.openai_host.clone(),
                 conf.proxy.clone(),
@@ -93,8 +96,7 @@ async fn run_jobs(redis: Arc<Redis>, conf: Arc<Conf>) -> anyhow::Result<()> {
         async move {
             match rust_blog::send_feishu_msg(
                 redis.as_ref(),
-                conf.rust_blog.webhooks.clone(),
-                conf.rust_blog.once_post_limit,
+                &conf.rust_blog,
                 conf.openai_api_key.clone(),
                 conf.openai_host.clone(),
                 conf.proxy.clone(),
@@ -117,8 +119,7 @@ async fn run_jobs(redis: Arc<Redis>, conf: Arc<Conf>) -> anyhow::Result<()> {
         async move {
             match rust_inside_blog::send_feishu_msg(
                 redis.as_ref(),
-                conf.rust_inside_blog.webhooks.clone(),
-                conf.rust_inside_blog.once_post_limit,
+                &conf.rust_inside_blog,
                 conf.openai_api_key.clone(),
                 conf.openai_host.clone(),
                 conf.proxy.clone(),
@@ -133,6 +134,249 @@ async fn run_jobs(redis: Arc<Redis>, conf: Arc<Conf>) -> anyhow::Result<()> {
     .await?;
     info!("add rust_inside_blog job");
 
+    if let Some(gr) = &conf.go_releases {
+        let redis_clone = redis.clone();
+        let conf_clone = conf.clone();
+        cron.add_fn(&gr.cron_expression, move || {
+            let redis = redis_clone.clone();
+            let conf = conf_clone.clone();
+            async move {
+                let Some(gr) = &conf.go_releases else {
+                    return;
+                };
+                match go_releases::send_feishu_msg(redis.as_ref(), gr, conf.proxy.clone()).await {
+                    Ok(_) => info!("send go releases msg success"),
+                    Err(e) => error!("send go releases msg failed: {}", e),
+                }
+            }
+        })
+        .await?;
+        info!("add go_releases job");
+    }
+
+    if let Some(twir) = &conf.this_week_in_rust {
+        let redis_clone = redis.clone();
+        let conf_clone = conf.clone();
+        cron.add_fn(&twir.source.cron_expression, move || {
+            let redis = redis_clone.clone();
+            let conf = conf_clone.clone();
+            async move {
+                let Some(twir) = &conf.this_week_in_rust else {
+                    return;
+                };
+                match this_week_in_rust::send_feishu_msg(redis.as_ref(), twir, conf.proxy.clone())
+                    .await
+                {
+                    Ok(_) => info!("send this week in rust msg success"),
+                    Err(e) => error!("send this week in rust msg failed: {}", e),
+                }
+            }
+        })
+        .await?;
+        info!("add this_week_in_rust job");
+    }
+
+    if let Some(gr) = &conf.github_releases {
+        let redis_clone = redis.clone();
+        let conf_clone = conf.clone();
+        cron.add_fn(&gr.source.cron_expression, move || {
+            let redis = redis_clone.clone();
+            let conf = conf_clone.clone();
+            async move {
+                let Some(gr) = &conf.github_releases else {
+                    return;
+                };
+                match github_releases::send_feishu_msg(
+                    redis.as_ref(),
+                    gr,
+                    conf.openai_api_key.clone(),
+                    conf.openai_host.clone(),
+                    conf.proxy.clone(),
+                )
+                .await
+                {
+                    Ok(_) => info!("send github releases msg success"),
+                    Err(e) => error!("send github releases msg failed: {}", e),
+                }
+            }
+        })
+        .await?;
+        info!("add github_releases job");
+    }
+
+    if let Some(ci) = &conf.crates_io {
+        let redis_clone = redis.clone();
+        let conf_clone = conf.clone();
+        cron.add_fn(&ci.source.cron_expression, move || {
+            let redis = redis_clone.clone();
+            let conf = conf_clone.clone();
+            async move {
+                let Some(ci) = &conf.crates_io else {
+                    return;
+                };
+                match crates_io::send_feishu_msg(redis.as_ref(), ci, conf.proxy.clone()).await {
+                    Ok(_) => info!("send crates.io msg success"),
+                    Err(e) => error!("send crates.io msg failed: {}", e),
+                }
+            }
+        })
+        .await?;
+        info!("add crates_io job");
+    }
+
+    for (db, ac) in [
+        (Database::RustSec, &conf.rustsec),
+        (Database::GoVulnDb, &conf.go_vulns),
+    ] {
+        let Some(ac) = ac else {
+            continue;
+        };
+        let redis_clone = redis.clone();
+        let conf_clone = conf.clone();
+        let ac_clone = ac.clone();
+        cron.add_fn(&ac.source.cron_expression, move || {
+            let redis = redis_clone.clone();
+            let proxy = conf_clone.proxy.clone();
+            let ac = ac_clone.clone();
+            async move {
+                match advisories::send_feishu_msg(redis.as_ref(), db, &ac, proxy).await {
+                    Ok(_) => info!("send {} advisories msg success", db.name()),
+                    Err(e) => error!("send {} advisories msg failed: {}", db.name(), e),
+                }
+            }
+        })
+        .await?;
+        info!("add {} advisories job", db.name());
+    }
+
+    for (i, nl) in conf.newsletters.iter().enumerate() {
+        let redis_clone = redis.clone();
+        let conf_clone = conf.clone();
+        cron.add_fn(&nl.source.cron_expression, move || {
+            let redis = redis_clone.clone();
+            let conf = conf_clone.clone();
+            async move {
+                let nl = &conf.newsletters[i];
+                match newsletter::send_feishu_msg(
+                    redis.as_ref(),
+                    &Newsletter::from(nl),
+                    &nl.source,
+                    conf.openai_api_key.clone(),
+                    conf.openai_host.clone(),
+                    conf.proxy.clone(),
+                )
+                .await
+                {
+                    Ok(_) => info!("send {} msg success", nl.name),
+                    Err(e) => error!("send {} msg failed: {}", nl.name, e),
+                }
+            }
+        })
+        .await?;
+        info!("add {} newsletter job", nl.name);
+    }
+
+    for (i, sc) in conf.scrapers.iter().enumerate() {
+        let redis_clone = redis.clone();
+        let conf_clone = conf.clone();
+        cron.add_fn(&sc.source.cron_expression, move || {
+            let redis = redis_clone.clone();
+            let conf = conf_clone.clone();
+            async move {
+                let sc = &conf.scrapers[i];
+                match scrape::send_feishu_msg(
+                    redis.as_ref(),
+                    sc,
+                    conf.openai_api_key.clone(),
+                    conf.openai_host.clone(),
+                    conf.proxy.clone(),
+                )
+                .await
+                {
+                    Ok(_) => info!("send {} msg success", sc.name),
+                    Err(e) => error!("send {} msg failed: {}", sc.name, e),
+                }
+            }
+        })
+        .await?;
+        info!("add {} scrape job", sc.name);
+    }
+
+    for (i, mf) in conf.media_feeds.iter().enumerate() {
+        let redis_clone = redis.clone();
+        let conf_clone = conf.clone();
+        cron.add_fn(&mf.source.cron_expression, move || {
+            let redis = redis_clone.clone();
+            let conf = conf_clone.clone();
+            async move {
+                let mf = &conf.media_feeds[i];
+                match media::send_feishu_msg(
+                    redis.as_ref(),
+                    mf,
+                    conf.feishu_app.as_ref(),
+                    conf.proxy.clone(),
+                )
+                .await
+                {
+                    Ok(_) => info!("send {} msg success", mf.name),
+                    Err(e) => error!("send {} msg failed: {}", mf.name, e),
+                }
+            }
+        })
+        .await?;
+        info!("add {} media job", mf.name);
+    }
+
+    for (i, mb) in conf.mailboxes.iter().enumerate() {
+        let redis_clone = redis.clone();
+        let conf_clone = conf.clone();
+        cron.add_fn(&mb.source.cron_expression, move || {
+            let redis = redis_clone.clone();
+            let conf = conf_clone.clone();
+            async move {
+                let mb = &conf.mailboxes[i];
+                match mailbox::send_feishu_msg(
+                    redis.as_ref(),
+                    mb,
+                    conf.openai_api_key.clone(),
+                    conf.openai_host.clone(),
+                    conf.proxy.clone(),
+                )
+                .await
+                {
+                    Ok(_) => info!("send {} msg success", mb.name),
+                    Err(e) => error!("send {} msg failed: {}", mb.name, e),
+                }
+            }
+        })
+        .await?;
+        info!("add {} mailbox job", mb.name);
+    }
+
+    if let Some(digest) = &conf.email_digest {
+        let redis_clone = redis.clone();
+        let conf_clone = conf.clone();
+        cron.add_fn(&digest.cron_expression, move || {
+            let redis = redis_clone.clone();
+            let conf = conf_clone.clone();
+            async move {
+                // the SMTP transport blocks, keep it off the threads of the other jobs
+                let res = tokio::task::spawn_blocking(move || match &conf.email_digest {
+                    Some(digest) => email::send_digest(redis.as_ref(), digest),
+                    None => Ok(()),
+                })
+                .await;
+                match res {
+                    Ok(Ok(_)) => info!("send email digest success"),
+                    Ok(Err(e)) => error!("send email digest failed: {}", e),
+                    Err(e) => error!("email digest task failed: {}", e),
+                }
+            }
+        })
+        .await?;
+        info!("add email_digest job");
+    }
+
     cron.start().await;
 
     info!("cron task started");
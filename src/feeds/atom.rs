@@ -16,11 +16,47 @@ pub struct Entry {
     pub title: String,
     pub id: String,
     pub link: Link,
+    // missing in some feeds, e.g. GitHub releases, see `Entry::date`.
+    #[serde(default)]
     pub published: String,
     pub updated: String,
     #[serde(default)]
     pub summary: String,
+    // some feeds, e.g. This Week in Rust, only carry a summary.
+    #[serde(default)]
     pub content: String,
+    // videos of YouTube feeds carry a `media:group` instead of a content.
+    #[serde(rename = "group", alias = "media:group", default)]
+    pub media: Option<MediaGroup>,
+}
+
+/// The `media:` namespace of [Media RSS](https://www.rssboard.org/media-rss),
+/// used by YouTube and podcast feeds.
+#[derive(Debug, Default, Deserialize)]
+pub struct MediaGroup {
+    #[serde(rename = "title", alias = "media:title", default)]
+    pub title: String,
+    #[serde(rename = "content", alias = "media:content", default)]
+    pub content: Option<MediaContent>,
+    #[serde(rename = "thumbnail", alias = "media:thumbnail", default)]
+    pub thumbnail: Option<MediaThumbnail>,
+    #[serde(rename = "description", alias = "media:description", default)]
+    pub description: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct MediaContent {
+    #[serde(rename = "@url")]
+    pub url: String,
+    // in seconds
+    #[serde(rename = "@duration", default)]
+    pub duration: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct MediaThumbnail {
+    #[serde(rename = "@url")]
+    pub url: String,
 }
 
 #[derive(Debug, Default, Deserialize)]
@@ -29,11 +65,26 @@ pub struct Link {
     pub href: String,
 }
 
+impl Entry {
+    /// Returns the publication date, or the update date if the feed has none.
+    pub fn date(&self) -> &str {
+        if self.published.is_empty() {
+            &self.updated
+        } else {
+            &self.published
+        }
+    }
+}
+
 impl Atom {
     pub async fn try_new(url: &str, proxy: Option<String>) -> anyhow::Result<Atom> {
         let data = send_request(url, proxy).await?;
         Ok(resolve_xml_data(&data)?)
     }
+
+    pub fn parse(data: &str) -> anyhow::Result<Atom> {
+        Ok(resolve_xml_data(data)?)
+    }
 }
 
 async fn send_request(url: &str, proxy: Option<String>) -> Result<String, reqwest::Error> {
@@ -111,6 +162,52 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn resolve_xml_data_from_this_week_in_rust_should_work() -> anyhow::Result<()> {
+        let data = include_str!("../../fixtures/this_week_in_rust.xml");
+        let atom = resolve_xml_data(data)?;
+        assert_eq!(atom.title, "This Week in Rust");
+        assert_eq!(atom.entry.len(), 2);
+        assert_eq!(atom.entry[0].title, "This Week in Rust 558");
+        assert!(atom.entry[0].content.is_empty());
+        assert!(atom.entry[0].summary.contains("Crate of the Week"));
+        Ok(())
+    }
+
+    #[test]
+    fn resolve_xml_data_from_github_releases_should_work() -> anyhow::Result<()> {
+        let data = include_str!("../../fixtures/github_releases.xml");
+        let atom = resolve_xml_data(data)?;
+        assert_eq!(atom.title, "Release notes from tokio");
+        assert_eq!(atom.entry.len(), 4);
+        assert!(atom.entry[1].published.is_empty());
+        assert_eq!(atom.entry[1].date(), "2024-07-23T14:22:08Z");
+        assert_eq!(
+            atom.entry[1].link.href,
+            "https://github.com/tokio-rs/tokio/releases/tag/tokio-1.39.0"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn resolve_xml_data_from_youtube_should_work() -> anyhow::Result<()> {
+        let data = include_str!("../../fixtures/youtube.xml");
+        let atom = resolve_xml_data(data)?;
+        assert_eq!(atom.title, "Rust");
+        assert_eq!(atom.entry.len(), 2);
+        let entry = &atom.entry[0];
+        assert!(entry.content.is_empty());
+        let media = entry.media.as_ref().unwrap();
+        assert_eq!(media.title, "RustConf 2024 - Keynote: Rust Project Goals");
+        assert_eq!(
+            media.thumbnail.as_ref().unwrap().url,
+            "https://i4.ytimg.com/vi/9fx4v2pQ3qg/hqdefault.jpg"
+        );
+        assert!(media.description.starts_with("Rust project goals"));
+        assert!(media.content.as_ref().unwrap().duration.is_none());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn try_new_from_rust_inside_blog_should_work() -> anyhow::Result<()> {
         let atom = Atom::try_new(RUST_INSIDE_BLOG_ATOM_URL, None).await?;
@@ -0,0 +1,97 @@
+use reqwest::{Client, Proxy};
+use serde::Deserialize;
+use tracing::info;
+
+/// crates.io asks API clients to identify themselves.
+const USER_AGENT: &str = "hedon-bot (https://github.com/hedon-rust-road/hedon-bot)";
+
+/// The response of `https://crates.io/api/v1/crates/<name>`.
+#[derive(Debug, Deserialize)]
+pub struct CrateInfo {
+    #[serde(rename = "crate")]
+    pub krate: Crate,
+    pub versions: Vec<CrateVersion>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Crate {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub repository: Option<String>,
+    #[serde(default)]
+    pub max_stable_version: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct CrateVersion {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub num: String,
+    pub yanked: bool,
+    pub created_at: String,
+    #[serde(default)]
+    pub license: Option<String>,
+    #[serde(default)]
+    pub published_by: Option<Publisher>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Publisher {
+    pub login: String,
+}
+
+impl CrateInfo {
+    pub async fn try_new(name: &str, proxy: Option<String>) -> anyhow::Result<CrateInfo> {
+        let url = format!("https://crates.io/api/v1/crates/{}", name);
+        let data = send_request(&url, proxy).await?;
+        CrateInfo::parse(&data)
+    }
+
+    pub fn parse(data: &str) -> anyhow::Result<CrateInfo> {
+        Ok(serde_json::from_str(data)?)
+    }
+}
+
+async fn send_request(url: &str, proxy: Option<String>) -> Result<String, reqwest::Error> {
+    info!(
+        "sending request to get crate data from {}, use proxy: {}",
+        url,
+        proxy.is_some()
+    );
+    let mut builder = Client::builder().user_agent(USER_AGENT);
+    if let Some(proxy) = proxy {
+        builder = builder.proxy(Proxy::https(proxy)?);
+    }
+    let resp = builder
+        .build()?
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    info!("get crate data from {} success", url);
+    Ok(resp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_crate_info_should_work() {
+        let data = include_str!("../../fixtures/crates_io_tokio.json");
+        let info = CrateInfo::parse(data).unwrap();
+        assert_eq!(info.krate.name, "tokio");
+        assert_eq!(
+            info.krate.repository.as_deref(),
+            Some("https://github.com/tokio-rs/tokio")
+        );
+        assert_eq!(info.versions.len(), 4);
+        assert_eq!(info.versions[0].num, "1.39.1");
+        assert!(info.versions[1].yanked);
+        assert!(info.versions[2].published_by.is_none());
+    }
+}
@@ -0,0 +1,248 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use native_tls::TlsConnector;
+use tracing::info;
+
+use crate::conf::ImapConfig;
+
+/// The timeout of connecting to and reading from the server.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A minimal read-only IMAP client, just enough to fetch the latest
+/// messages of a mailbox.
+struct Session<S: Read + Write> {
+    stream: BufReader<S>,
+    tag: u32,
+}
+
+/// The untagged lines of a command response and the literals they carry,
+/// e.g. the messages of a `FETCH`.
+#[derive(Debug, Default)]
+struct Response {
+    lines: Vec<String>,
+    literals: Vec<Vec<u8>>,
+}
+
+impl<S: Read + Write> Session<S> {
+    fn new(stream: S) -> anyhow::Result<Self> {
+        let mut session = Session {
+            stream: BufReader::new(stream),
+            tag: 0,
+        };
+        let greeting = session.read_line()?;
+        if !greeting.starts_with("* OK") {
+            return Err(anyhow!("unexpected imap greeting: {}", greeting.trim()));
+        }
+        Ok(session)
+    }
+
+    fn read_line(&mut self) -> anyhow::Result<String> {
+        let mut line = String::new();
+        if self.stream.read_line(&mut line)? == 0 {
+            return Err(anyhow!("imap connection closed"));
+        }
+        Ok(line)
+    }
+
+    fn command(&mut self, command: &str) -> anyhow::Result<Response> {
+        self.tag += 1;
+        let tag = format!("A{}", self.tag);
+        let stream = self.stream.get_mut();
+        stream.write_all(format!("{} {}\r\n", tag, command).as_bytes())?;
+        stream.flush()?;
+
+        let mut resp = Response::default();
+        loop {
+            let line = self.read_line()?;
+            if let Some(status) = line.strip_prefix(&tag) {
+                if !status.trim_start().starts_with("OK") {
+                    let verb = command.split(' ').next().unwrap_or_default();
+                    return Err(anyhow!("imap {} failed: {}", verb, status.trim()));
+                }
+                return Ok(resp);
+            }
+            if let Some(size) = literal_size(&line) {
+                let mut literal = vec![0; size];
+                self.stream.read_exact(&mut literal)?;
+                resp.literals.push(literal);
+            }
+            resp.lines.push(line);
+        }
+    }
+}
+
+/// Returns the size of the literal announced at the end of the line, e.g.
+/// `* 1 FETCH (UID 7 BODY[] {2048}`.
+fn literal_size(line: &str) -> Option<usize> {
+    let line = line.trim_end().strip_suffix('}')?;
+    let start = line.rfind('{')?;
+    line[start + 1..].parse().ok()
+}
+
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Connects to the first reachable address of the host, giving up on each
+/// after [`TIMEOUT`].
+fn connect(host: &str, port: u16) -> anyhow::Result<TcpStream> {
+    let mut last_err = None;
+    for addr in (host, port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_err = Some(e),
+        }
+    }
+    Err(match last_err {
+        Some(e) => e.into(),
+        None => anyhow!("{} resolves to no address", host),
+    })
+}
+
+/// Fetches the raw `max` latest messages of the configured mailbox, without
+/// marking them as seen.
+pub fn fetch_messages(conf: &ImapConfig, max: usize) -> anyhow::Result<Vec<Vec<u8>>> {
+    info!(
+        "connecting to imap server {}:{}, use tls: {}",
+        conf.host, conf.port, conf.tls
+    );
+    let stream = connect(&conf.host, conf.port)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    if conf.tls {
+        let stream = TlsConnector::new()?.connect(&conf.host, stream)?;
+        fetch_from_stream(stream, conf, max)
+    } else {
+        fetch_from_stream(stream, conf, max)
+    }
+}
+
+fn fetch_from_stream<S: Read + Write>(
+    stream: S,
+    conf: &ImapConfig,
+    max: usize,
+) -> anyhow::Result<Vec<Vec<u8>>> {
+    let mut session = Session::new(stream)?;
+    session.command(&format!(
+        "LOGIN {} {}",
+        quote(&conf.username),
+        quote(&conf.password)
+    ))?;
+    session.command(&format!("EXAMINE {}", quote(&conf.mailbox)))?;
+    let resp = session.command("UID SEARCH ALL")?;
+    let mut uids = resp
+        .lines
+        .iter()
+        .filter_map(|v| v.strip_prefix("* SEARCH"))
+        .flat_map(|v| v.split_whitespace())
+        .filter_map(|v| v.parse::<u32>().ok())
+        .collect::<Vec<_>>();
+    uids.sort_unstable();
+    let uids = &uids[uids.len().saturating_sub(max)..];
+
+    let mut messages = Vec::with_capacity(uids.len());
+    for uid in uids {
+        let resp = session.command(&format!("UID FETCH {} BODY.PEEK[]", uid))?;
+        messages.extend(resp.literals);
+    }
+    // The messages are fetched, a failed logout does not matter.
+    let _ = session.command("LOGOUT");
+    info!(
+        "fetch {} messages from imap server {} success",
+        messages.len(),
+        conf.host
+    );
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use super::*;
+
+    const MESSAGE: &str = "Subject: Hello\r\n\r\nWorld\r\n";
+
+    /// A local stand-in for an IMAP server, replying to the commands of
+    /// `fetch_messages` in order.
+    fn serve(listener: TcpListener) -> Vec<String> {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut writer = stream;
+        writer.write_all(b"* OK IMAP4rev1 ready\r\n").unwrap();
+        let mut commands = vec![];
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap() == 0 {
+                break;
+            }
+            let (tag, command) = line.trim_end().split_once(' ').unwrap();
+            let reply = match command {
+                v if v.starts_with("LOGIN") => format!("{} OK LOGIN completed\r\n", tag),
+                v if v.starts_with("EXAMINE") => {
+                    format!("* 3 EXISTS\r\n{} OK [READ-ONLY] EXAMINE completed\r\n", tag)
+                }
+                "UID SEARCH ALL" => format!("* SEARCH 3 9 5\r\n{} OK SEARCH completed\r\n", tag),
+                v if v.starts_with("UID FETCH") => format!(
+                    "* 1 FETCH (UID 9 BODY[] {{{}}}\r\n{})\r\n{} OK FETCH completed\r\n",
+                    MESSAGE.len(),
+                    MESSAGE,
+                    tag
+                ),
+                "LOGOUT" => format!("* BYE\r\n{} OK LOGOUT completed\r\n", tag),
+                _ => format!("{} BAD unknown command\r\n", tag),
+            };
+            writer.write_all(reply.as_bytes()).unwrap();
+            commands.push(command.to_string());
+            if command == "LOGOUT" {
+                break;
+            }
+        }
+        commands
+    }
+
+    #[test]
+    fn test_literal_size() {
+        assert_eq!(
+            literal_size("* 1 FETCH (UID 7 BODY[] {2048}\r\n"),
+            Some(2048)
+        );
+        assert_eq!(literal_size("* SEARCH 1 2\r\n"), None);
+    }
+
+    #[test]
+    fn test_fetch_messages() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || serve(listener));
+        let conf = ImapConfig {
+            host: "127.0.0.1".to_string(),
+            port,
+            username: "bot".to_string(),
+            password: "p\"ss".to_string(),
+            mailbox: "INBOX".to_string(),
+            tls: false,
+        };
+        let messages = fetch_messages(&conf, 2).unwrap();
+        assert_eq!(messages, vec![MESSAGE.as_bytes(); 2]);
+        assert_eq!(
+            server.join().unwrap(),
+            vec![
+                "LOGIN \"bot\" \"p\\\"ss\"",
+                "EXAMINE \"INBOX\"",
+                "UID SEARCH ALL",
+                "UID FETCH 5 BODY.PEEK[]",
+                "UID FETCH 9 BODY.PEEK[]",
+                "LOGOUT",
+            ]
+        );
+    }
+}
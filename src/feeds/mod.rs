@@ -1,5 +1,11 @@
 mod atom;
+mod crates_io;
+mod imap;
+mod osv;
 mod rss;
 
 pub use atom::*;
+pub use crates_io::*;
+pub use imap::*;
+pub use osv::*;
 pub use rss::*;
@@ -0,0 +1,255 @@
+use std::{fs, path::Path};
+
+use reqwest::{Client, Proxy};
+use serde::Deserialize;
+use serde_json::json;
+use tracing::{error, info};
+
+const OSV_QUERY_URL: &str = "https://api.osv.dev/v1/query";
+
+/// An advisory in the [OSV format](https://ossf.github.io/osv-schema/),
+/// shared by RustSec and the Go vulnerability database.
+#[derive(Debug, Default, Clone, Deserialize, PartialEq)]
+pub struct Advisory {
+    pub id: String,
+    pub modified: String,
+    #[serde(default)]
+    pub published: String,
+    #[serde(default)]
+    pub withdrawn: Option<String>,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub summary: String,
+    #[serde(default)]
+    pub details: String,
+    #[serde(default)]
+    pub severity: Vec<Severity>,
+    #[serde(default)]
+    pub affected: Vec<Affected>,
+    #[serde(default)]
+    pub references: Vec<Reference>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, PartialEq)]
+pub struct Severity {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub score: String,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, PartialEq)]
+pub struct Affected {
+    pub package: Package,
+    #[serde(default)]
+    pub ranges: Vec<Range>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, PartialEq)]
+pub struct Package {
+    pub ecosystem: String,
+    pub name: String,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, PartialEq)]
+pub struct Range {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub events: Vec<Event>,
+}
+
+/// Only one of the fields is set in an event.
+#[derive(Debug, Default, Clone, Deserialize, PartialEq)]
+pub struct Event {
+    #[serde(default)]
+    pub introduced: Option<String>,
+    #[serde(default)]
+    pub fixed: Option<String>,
+    #[serde(default)]
+    pub last_affected: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize, PartialEq)]
+pub struct Reference {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct QueryResp {
+    #[serde(default)]
+    vulns: Vec<Advisory>,
+    // set when more advisories are left, to be passed as `page_token`
+    #[serde(default)]
+    next_page_token: Option<String>,
+}
+
+impl Advisory {
+    /// Queries `api.osv.dev` for the advisories of a package, `ecosystem`
+    /// is e.g. `crates.io` or `Go`.
+    pub async fn query(
+        ecosystem: &str,
+        package: &str,
+        proxy: Option<String>,
+    ) -> anyhow::Result<Vec<Advisory>> {
+        info!(
+            "sending request to get advisories of {} {}, use proxy: {}",
+            ecosystem,
+            package,
+            proxy.is_some()
+        );
+        let client: Client;
+        if let Some(proxy) = proxy {
+            let proxy = Proxy::https(proxy)?;
+            client = Client::builder().proxy(proxy).build()?;
+        } else {
+            client = reqwest::Client::new();
+        }
+        let advisories = query_pages(&client, OSV_QUERY_URL, ecosystem, package).await?;
+        info!(
+            "get advisories of {} {} success, count: {}",
+            ecosystem,
+            package,
+            advisories.len()
+        );
+        Ok(advisories)
+    }
+
+    pub fn parse(data: &str) -> anyhow::Result<Advisory> {
+        Ok(serde_json::from_str(data)?)
+    }
+
+    /// Reads the advisories of a local directory of OSV files, e.g. the
+    /// `crates` directory of a checkout of the RustSec `osv` branch.
+    /// Files that are not advisories are skipped.
+    pub fn read_dir(path: &Path) -> anyhow::Result<Vec<Advisory>> {
+        let mut advisories = vec![];
+        for entry in fs::read_dir(path)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|v| v != "json") {
+                continue;
+            }
+            match fs::read_to_string(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|v| Advisory::parse(&v))
+            {
+                Ok(v) => advisories.push(v),
+                Err(e) => error!("read advisory {} failed: {}", path.display(), e),
+            }
+        }
+        Ok(advisories)
+    }
+
+    /// Returns whether the advisory affects `package` of `ecosystem`.
+    pub fn affects(&self, ecosystem: &str, package: &str) -> bool {
+        self.affected
+            .iter()
+            .any(|v| v.package.ecosystem == ecosystem && v.package.name == package)
+    }
+}
+
+/// Sends the query to `url` until the response has no `next_page_token`,
+/// large packages don't fit in one page.
+async fn query_pages(
+    client: &Client,
+    url: &str,
+    ecosystem: &str,
+    package: &str,
+) -> anyhow::Result<Vec<Advisory>> {
+    let mut advisories = vec![];
+    let mut page_token: Option<String> = None;
+    loop {
+        let mut req = json!({
+            "package": {
+                "name": package,
+                "ecosystem": ecosystem,
+            }
+        });
+        if let Some(token) = page_token {
+            req["page_token"] = json!(token);
+        }
+        let data = client
+            .post(url)
+            .json(&req)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let resp = parse_query_resp(&data)?;
+        advisories.extend(resp.vulns);
+        page_token = resp.next_page_token.filter(|v| !v.is_empty());
+        if page_token.is_none() {
+            return Ok(advisories);
+        }
+    }
+}
+
+fn parse_query_resp(data: &str) -> Result<QueryResp, serde_json::Error> {
+    serde_json::from_str(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, thread};
+
+    use crate::notifier::test_server::serve;
+
+    use super::*;
+
+    #[test]
+    fn parse_advisory_should_work() {
+        let data = include_str!("../../fixtures/osv_rustsec.json");
+        let advisory = Advisory::parse(data).unwrap();
+        assert_eq!(advisory.id, "RUSTSEC-2023-0005");
+        assert_eq!(advisory.aliases, vec!["GHSA-4q83-7cq4-p6wg"]);
+        assert_eq!(advisory.severity[0].kind, "CVSS_V3");
+        assert!(advisory.affects("crates.io", "tokio"));
+        assert!(!advisory.affects("Go", "tokio"));
+        assert_eq!(advisory.affected[0].ranges[0].events.len(), 6);
+    }
+
+    #[test]
+    fn parse_query_resp_should_work() {
+        let data = include_str!("../../fixtures/osv_go_query.json");
+        let advisories = parse_query_resp(data).unwrap().vulns;
+        assert_eq!(advisories.len(), 2);
+        assert_eq!(advisories[0].id, "GO-2024-2687");
+        assert!(advisories[0].affects("Go", "golang.org/x/net"));
+        assert!(advisories[0].severity.is_empty());
+        assert!(parse_query_resp("{}").unwrap().vulns.is_empty());
+    }
+
+    #[tokio::test]
+    async fn query_pages_should_follow_page_token() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            serve(
+                listener,
+                vec![
+                    (
+                        200,
+                        r#"{"vulns":[{"id":"GO-2024-2687","modified":"2024-04-03T00:00:00Z"}],"next_page_token":"page-2"}"#,
+                    ),
+                    (
+                        200,
+                        r#"{"vulns":[{"id":"GO-2023-2102","modified":"2023-10-11T00:00:00Z"}]}"#,
+                    ),
+                ],
+            )
+        });
+
+        let url = format!("http://127.0.0.1:{}/v1/query", port);
+        let advisories = query_pages(&Client::new(), &url, "Go", "golang.org/x/net")
+            .await
+            .unwrap();
+        let ids = advisories.iter().map(|v| v.id.as_str()).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["GO-2024-2687", "GO-2023-2102"]);
+        let requests = server.join().unwrap();
+        assert!(!requests[0].1.contains("page_token"));
+        assert!(requests[1].1.contains(r#""page_token":"page-2""#));
+    }
+}
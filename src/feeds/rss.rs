@@ -2,6 +2,8 @@ use quick_xml::de::from_str;
 use serde::Deserialize;
 use tracing::info;
 
+use super::{MediaContent, MediaThumbnail};
+
 #[derive(Debug, Deserialize)]
 pub struct Feed {
     pub channel: Channel,
@@ -27,6 +29,32 @@ pub struct Item {
     pub content: String,
     #[serde(rename = "creator", alias = "dc:creator", default)]
     pub creator: String,
+    // the audio or video file of podcast episodes.
+    #[serde(default)]
+    pub enclosure: Option<Enclosure>,
+    // `3125` or `1:04:37`
+    #[serde(rename = "duration", alias = "itunes:duration", default)]
+    pub duration: Option<String>,
+    #[serde(rename = "image", alias = "itunes:image", default)]
+    pub image: Option<ItunesImage>,
+    #[serde(rename = "thumbnail", alias = "media:thumbnail", default)]
+    pub thumbnail: Option<MediaThumbnail>,
+    #[serde(rename = "content", alias = "media:content", default)]
+    pub media_content: Option<MediaContent>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Enclosure {
+    #[serde(rename = "@url")]
+    pub url: String,
+    #[serde(rename = "@type", default)]
+    pub mime_type: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ItunesImage {
+    #[serde(rename = "@href")]
+    pub href: String,
 }
 
 impl Feed {
@@ -35,6 +63,10 @@ impl Feed {
         let resp = send_request(url).await?;
         Ok(resolve_xml_data(&resp)?)
     }
+
+    pub fn parse(data: &str) -> anyhow::Result<Self> {
+        Ok(resolve_xml_data(data)?)
+    }
 }
 
 async fn send_request(url: &str) -> Result<String, reqwest::Error> {
@@ -68,4 +100,25 @@ mod tests {
         assert_eq!(feed.channel.items.len(), 12);
         Ok(())
     }
+
+    #[test]
+    fn resolve_podcast_xml_data_should_work() -> anyhow::Result<()> {
+        let data = include_str!("../../fixtures/podcast.xml");
+        let feed = resolve_xml_data(data)?;
+        assert_eq!(feed.channel.items.len(), 2);
+        let item = &feed.channel.items[0];
+        assert_eq!(item.duration.as_deref(), Some("1:04:37"));
+        assert_eq!(
+            item.image.as_ref().unwrap().href,
+            "https://cdn.changelog.com/uploads/covers/go-time-330.png"
+        );
+        assert_eq!(item.enclosure.as_ref().unwrap().mime_type, "audio/mpeg");
+        let item = &feed.channel.items[1];
+        assert!(item.image.is_none());
+        assert_eq!(
+            item.thumbnail.as_ref().unwrap().url,
+            "https://cdn.changelog.com/uploads/covers/go-time-329.png"
+        );
+        Ok(())
+    }
 }
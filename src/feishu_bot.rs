@@ -1,7 +1,593 @@
-use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hmac::{Hmac, Mac};
+use reqwest::multipart::{Form, Part};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sha2::Sha256;
+
+use crate::conf::FeishuAppConfig;
+
+const TENANT_ACCESS_TOKEN_URL: &str =
+    "https://open.feishu.cn/open-apis/auth/v3/tenant_access_token/internal";
+const UPLOAD_IMAGE_URL: &str = "https://open.feishu.cn/open-apis/im/v1/images";
+
+/// A tenant access token is renewed this many seconds before it expires.
+const TOKEN_EXPIRY_MARGIN: u64 = 300;
+
+/// An interactive message, the request body of a custom robot webhook.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CardMessage {
+    msg_type: &'static str,
+    pub card: Card,
+    // set by `sign` for robots with signature verification
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sign: Option<String>,
+}
+
+impl CardMessage {
+    pub fn new(card: Card) -> Self {
+        CardMessage {
+            msg_type: "interactive",
+            card,
+            timestamp: None,
+            sign: None,
+        }
+    }
+
+    /// Signs the message with the secret of the robot, `timestamp` in seconds
+    /// must be within an hour of its clock.
+    pub fn sign(mut self, secret: &str, timestamp: i64) -> Self {
+        self.timestamp = Some(timestamp.to_string());
+        self.sign = Some(sign(secret, timestamp));
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Card {
+    pub elements: Vec<CardElement>,
+    pub header: Header,
+}
+
+impl Card {
+    pub fn new(title: impl Into<String>, template: Template) -> Self {
+        Card {
+            elements: vec![],
+            header: Header {
+                title: Text::plain(title),
+                template,
+            },
+        }
+    }
+
+    pub fn markdown(mut self, content: impl Into<String>) -> Self {
+        self.elements.push(CardElement::Markdown {
+            content: content.into(),
+        });
+        self
+    }
+
+    pub fn divider(mut self) -> Self {
+        self.elements.push(CardElement::Hr);
+        self
+    }
+
+    /// Adds a line of small grey text, e.g. a source or a disclaimer.
+    pub fn note(mut self, content: impl Into<String>) -> Self {
+        self.elements.push(CardElement::Note {
+            elements: vec![Text::lark_md(content)],
+        });
+        self
+    }
+
+    /// Adds an image uploaded to Feishu, cards cannot show remote images.
+    pub fn image(mut self, img_key: impl Into<String>, alt: impl Into<String>) -> Self {
+        self.elements.push(CardElement::Img {
+            img_key: img_key.into(),
+            alt: Text::plain(alt),
+        });
+        self
+    }
+
+    /// Adds a link button, next to the previous one if the card ends with buttons.
+    pub fn button(mut self, text: impl Into<String>, url: impl Into<String>) -> Self {
+        let button = Button::new(text, url);
+        match self.elements.last_mut() {
+            Some(CardElement::Action { actions }) => actions.push(button),
+            _ => self.elements.push(CardElement::Action {
+                actions: vec![button],
+            }),
+        }
+        self
+    }
+
+    /// Adds columns of equal width side by side.
+    pub fn columns(mut self, columns: Vec<Vec<CardElement>>) -> Self {
+        self.elements.push(CardElement::ColumnSet {
+            flex_mode: "none",
+            background_style: "default",
+            columns: columns.into_iter().map(Column::new).collect(),
+        });
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Header {
+    pub title: Text,
+    pub template: Template,
+}
+
+/// The color of the header of a card.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Template {
+    Blue,
+    Wathet,
+    Turquoise,
+    Green,
+    Yellow,
+    Orange,
+    Red,
+    Carmine,
+    Violet,
+    Purple,
+    Indigo,
+    Grey,
+}
+
+impl Template {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Template::Blue => "blue",
+            Template::Wathet => "wathet",
+            Template::Turquoise => "turquoise",
+            Template::Green => "green",
+            Template::Yellow => "yellow",
+            Template::Orange => "orange",
+            Template::Red => "red",
+            Template::Carmine => "carmine",
+            Template::Violet => "violet",
+            Template::Purple => "purple",
+            Template::Indigo => "indigo",
+            Template::Grey => "grey",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "tag", rename_all = "snake_case")]
+pub enum Text {
+    PlainText { content: String },
+    LarkMd { content: String },
+}
+
+impl Text {
+    pub fn plain(content: impl Into<String>) -> Self {
+        Text::PlainText {
+            content: content.into(),
+        }
+    }
+
+    pub fn lark_md(content: impl Into<String>) -> Self {
+        Text::LarkMd {
+            content: content.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "tag", rename_all = "snake_case")]
+pub enum CardElement {
+    Markdown {
+        content: String,
+    },
+    // a divider
+    Hr,
+    Note {
+        elements: Vec<Text>,
+    },
+    Img {
+        img_key: String,
+        alt: Text,
+    },
+    Action {
+        actions: Vec<Button>,
+    },
+    ColumnSet {
+        flex_mode: &'static str,
+        background_style: &'static str,
+        columns: Vec<Column>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "tag", rename = "button")]
+pub struct Button {
+    pub text: Text,
+    pub url: String,
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub value: Map<String, Value>,
+}
+
+impl Button {
+    pub fn new(text: impl Into<String>, url: impl Into<String>) -> Self {
+        Button {
+            text: Text::lark_md(text),
+            url: url.into(),
+            kind: "default",
+            value: Map::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "tag", rename = "column")]
+pub struct Column {
+    pub width: &'static str,
+    pub weight: u32,
+    pub vertical_align: &'static str,
+    pub elements: Vec<CardElement>,
+}
+
+impl Column {
+    pub fn new(elements: Vec<CardElement>) -> Self {
+        Column {
+            width: "weighted",
+            weight: 1,
+            vertical_align: "top",
+            elements,
+        }
+    }
+}
 
 #[derive(Debug, Deserialize)]
 pub struct SendMessageResp {
+    #[serde(default)]
     pub code: i64,
+    #[serde(default)]
     pub msg: String,
 }
+
+impl SendMessageResp {
+    pub fn failure(&self) -> Option<SendFailure> {
+        if self.code == 0 {
+            return None;
+        }
+        let msg = self.msg.to_lowercase();
+        let kind = match self.code {
+            11232 => FailureKind::RateLimited,
+            19001 | 19007 => FailureKind::InvalidWebhook,
+            19021 => FailureKind::SignatureMismatch,
+            19022 => FailureKind::IpNotAllowed,
+            19024 => FailureKind::KeywordNotFound,
+            _ if msg.contains("too large") || msg.contains("too long") => {
+                FailureKind::ContentTooLong
+            }
+            _ if msg.contains("frequency") || msg.contains("rate limit") => {
+                FailureKind::RateLimited
+            }
+            _ => FailureKind::Other,
+        };
+        Some(SendFailure {
+            kind,
+            code: self.code,
+            msg: self.msg.clone(),
+        })
+    }
+}
+
+/// Why a robot rejected a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    // too many messages, the robot accepts 100 a minute and 5 a second.
+    RateLimited,
+    // a 5xx response or a network error.
+    Unavailable,
+    // the webhook was deleted or its robot disabled.
+    InvalidWebhook,
+    // the secret or the clock is wrong.
+    SignatureMismatch,
+    // the server is not in the ip whitelist of the robot.
+    IpNotAllowed,
+    // the message lacks the custom keywords of the robot.
+    KeywordNotFound,
+    // the card is over the request size limit.
+    ContentTooLong,
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SendFailure {
+    pub kind: FailureKind,
+    // the code of the response, or the HTTP status when it has none
+    pub code: i64,
+    pub msg: String,
+}
+
+impl SendFailure {
+    /// Classifies a response without the JSON body of the robot API.
+    pub fn from_status(status: u16, body: &str) -> Self {
+        let kind = match status {
+            429 => FailureKind::RateLimited,
+            413 => FailureKind::ContentTooLong,
+            500..=599 => FailureKind::Unavailable,
+            _ => FailureKind::Other,
+        };
+        SendFailure {
+            kind,
+            code: status as i64,
+            msg: body.to_string(),
+        }
+    }
+
+    /// Whether sending the same message again later may succeed.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self.kind,
+            FailureKind::RateLimited | FailureKind::Unavailable
+        )
+    }
+}
+
+impl std::fmt::Display for SendFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}, code: {}, msg: {}", self.kind, self.code, self.msg)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TenantAccessTokenResp {
+    code: i64,
+    msg: String,
+    #[serde(default)]
+    tenant_access_token: String,
+    // in seconds
+    #[serde(default)]
+    expire: u64,
+}
+
+/// The token of a Feishu app, valid for about 2 hours.
+#[derive(Debug, Clone)]
+pub struct TenantAccessToken {
+    pub token: String,
+    pub expires_at: Instant,
+}
+
+impl TenantAccessToken {
+    pub fn is_valid(&self) -> bool {
+        self.expires_at > Instant::now()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct UploadImageResp {
+    code: i64,
+    msg: String,
+    data: Option<UploadImageData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UploadImageData {
+    image_key: String,
+}
+
+/// The `sign` of a request to a robot with signature verification: the
+/// HMAC-SHA256, keyed by `{timestamp}\n{secret}`, of an empty message.
+pub fn sign(secret: &str, timestamp: i64) -> String {
+    let key = format!("{}\n{}", timestamp, secret);
+    let mac =
+        Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC can take a key of any size");
+    STANDARD.encode(mac.finalize().into_bytes())
+}
+
+/// Gets a tenant access token of the app, which the image uploads need.
+pub async fn tenant_access_token(
+    client: &reqwest::Client,
+    app: &FeishuAppConfig,
+) -> anyhow::Result<TenantAccessToken> {
+    let res: TenantAccessTokenResp = client
+        .post(TENANT_ACCESS_TOKEN_URL)
+        .json(&json!({
+            "app_id": app.app_id,
+            "app_secret": app.app_secret,
+        }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    if res.code != 0 {
+        return Err(anyhow!(
+            "get tenant access token failed, code: {}, msg: {}",
+            res.code,
+            res.msg
+        ));
+    }
+    let valid_for = res.expire.saturating_sub(TOKEN_EXPIRY_MARGIN);
+    Ok(TenantAccessToken {
+        token: res.tenant_access_token,
+        expires_at: Instant::now() + Duration::from_secs(valid_for),
+    })
+}
+
+/// Downloads the image at `url` and uploads it to Feishu, returning the
+/// `img_key` cards refer to. Webhook robots cannot upload images, it needs
+/// a tenant access token of a Feishu app.
+pub async fn upload_image(
+    client: &reqwest::Client,
+    token: &str,
+    url: &str,
+) -> anyhow::Result<String> {
+    let image = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    let form = Form::new()
+        .text("image_type", "message")
+        .part("image", Part::bytes(image.to_vec()).file_name("image"));
+    let res: UploadImageResp = client
+        .post(UPLOAD_IMAGE_URL)
+        .bearer_auth(token)
+        .multipart(form)
+        .send()
+        .await?
+        .json()
+        .await?;
+    match res.data {
+        Some(data) if res.code == 0 => Ok(data.image_key),
+        _ => Err(anyhow!(
+            "upload image failed, code: {}, msg: {}",
+            res.code,
+            res.msg
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_card_message() {
+        let card = Card::new("Announcing Rust 1.80", Template::Orange)
+            .markdown("content")
+            .divider()
+            .note("generated by OpenAI")
+            .image("img_v3_xxx", "thumbnail")
+            .button("origin link", "https://example.com/a")
+            .button("docs", "https://example.com/b");
+        assert_eq!(
+            serde_json::to_value(CardMessage::new(card)).unwrap(),
+            json!({
+                "msg_type": "interactive",
+                "card": {
+                    "elements": [
+                        { "tag": "markdown", "content": "content" },
+                        { "tag": "hr" },
+                        {
+                            "tag": "note",
+                            "elements": [{ "tag": "lark_md", "content": "generated by OpenAI" }]
+                        },
+                        {
+                            "tag": "img",
+                            "img_key": "img_v3_xxx",
+                            "alt": { "tag": "plain_text", "content": "thumbnail" }
+                        },
+                        {
+                            "tag": "action",
+                            "actions": [
+                                {
+                                    "tag": "button",
+                                    "text": { "tag": "lark_md", "content": "origin link" },
+                                    "url": "https://example.com/a",
+                                    "type": "default",
+                                    "value": {}
+                                },
+                                {
+                                    "tag": "button",
+                                    "text": { "tag": "lark_md", "content": "docs" },
+                                    "url": "https://example.com/b",
+                                    "type": "default",
+                                    "value": {}
+                                }
+                            ]
+                        }
+                    ],
+                    "header": {
+                        "title": { "tag": "plain_text", "content": "Announcing Rust 1.80" },
+                        "template": "orange"
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_columns() {
+        let card = Card::new("Go 1.22.5", Template::Blue).columns(vec![
+            vec![CardElement::Markdown {
+                content: "**Version**\n1.22.5".to_string(),
+            }],
+            vec![CardElement::Markdown {
+                content: "**Date**\n2024-07-02".to_string(),
+            }],
+        ]);
+        let value = serde_json::to_value(&card.elements[0]).unwrap();
+        assert_eq!(value["tag"], "column_set");
+        assert_eq!(value["flex_mode"], "none");
+        assert_eq!(value["columns"][1]["tag"], "column");
+        assert_eq!(value["columns"][1]["width"], "weighted");
+        assert_eq!(
+            value["columns"][1]["elements"][0]["content"],
+            "**Date**\n2024-07-02"
+        );
+    }
+
+    #[test]
+    fn test_send_failure() {
+        let resp = |code: i64, msg: &str| SendMessageResp {
+            code,
+            msg: msg.to_string(),
+        };
+        assert_eq!(resp(0, "success").failure(), None);
+        let failure = resp(
+            11232,
+            "frequency limited psm[lark.oapi.app_platform_runtime]",
+        )
+        .failure()
+        .unwrap();
+        assert_eq!(failure.kind, FailureKind::RateLimited);
+        assert!(failure.is_transient());
+        let failure = resp(19024, "Key Words Not Found").failure().unwrap();
+        assert_eq!(failure.kind, FailureKind::KeywordNotFound);
+        assert!(!failure.is_transient());
+        assert_eq!(
+            resp(
+                19001,
+                "param invalid: incoming webhook access token invalid"
+            )
+            .failure()
+            .unwrap()
+            .kind,
+            FailureKind::InvalidWebhook
+        );
+        assert_eq!(
+            resp(9499, "request body too large").failure().unwrap().kind,
+            FailureKind::ContentTooLong
+        );
+        assert_eq!(
+            SendFailure::from_status(502, "Bad Gateway").kind,
+            FailureKind::Unavailable
+        );
+    }
+
+    #[test]
+    fn test_signed_card_message() {
+        let msg = CardMessage::new(Card::new("title", Template::Grey));
+        let value = serde_json::to_value(&msg).unwrap();
+        assert!(value.get("sign").is_none());
+        let value = serde_json::to_value(msg.sign("xxx", 1721894400)).unwrap();
+        assert_eq!(value["timestamp"], "1721894400");
+        assert_eq!(
+            value["sign"],
+            "VJlFR6XzFnCqegJjodcqtue2lMQiwd5aAK3LujTuG84="
+        );
+    }
+
+    #[test]
+    fn test_sign() {
+        assert_eq!(
+            sign("xxx", 1721894400),
+            "VJlFR6XzFnCqegJjodcqtue2lMQiwd5aAK3LujTuG84="
+        );
+    }
+}
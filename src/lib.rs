@@ -1,13 +1,19 @@
+use std::cmp::Ordering;
+
+use chrono::{DateTime, FixedOffset};
+use conf::PostOrder;
 use regex::Regex;
 
 pub mod channels;
 
+pub mod canonical;
 pub mod chatgpt;
 pub mod conf;
 pub mod cron_task;
 pub mod feeds;
 pub mod feishu_bot;
 pub mod log;
+pub mod notifier;
 pub mod redis_base;
 
 pub const DEFAULT_ONCE_POST_LIMIT: u8 = 5;
@@ -28,6 +34,46 @@ pub fn remove_tailing_slash(s: &str) -> &str {
     }
 }
 
+/// Truncates `s` to at most `max_chars` characters, marking the cut with `...`.
+pub fn truncate_str(s: &str, max_chars: usize) -> String {
+    if s.chars().count() <= max_chars {
+        return s.to_string();
+    }
+    let mut res = s.chars().take(max_chars).collect::<String>();
+    res.push_str("...");
+    res
+}
+
+/// Parses a feed date, accepting RFC 3339 (Atom) and RFC 2822 (RSS).
+pub fn parse_date(s: &str) -> Option<DateTime<FixedOffset>> {
+    let s = s.trim();
+    DateTime::parse_from_rfc3339(s)
+        .or_else(|_| DateTime::parse_from_rfc2822(s))
+        .ok()
+}
+
+/// Sorts feed items by their parsed date according to `order`.
+///
+/// Items whose date cannot be parsed keep their feed order and are placed
+/// after the dated ones.
+pub fn sort_by_date<T, F>(items: &mut [T], order: PostOrder, date: F)
+where
+    F: Fn(&T) -> &str,
+{
+    if order == PostOrder::FeedOrder {
+        return;
+    }
+    items.sort_by(|a, b| match (parse_date(date(a)), parse_date(date(b))) {
+        (Some(a), Some(b)) => match order {
+            PostOrder::NewestFirst => b.cmp(&a),
+            _ => a.cmp(&b),
+        },
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    });
+}
+
 #[cfg(test)]
 mod test_trim_str {
     use super::*;
@@ -84,3 +130,64 @@ mod test_remove_trailing_slash {
         assert_eq!(remove_tailing_slash(s), "https://example.com");
     }
 }
+
+#[cfg(test)]
+mod test_truncate_str {
+    use super::*;
+
+    #[test]
+    fn no_need_to_truncate() {
+        assert_eq!(truncate_str("Hello", 5), "Hello");
+    }
+
+    #[test]
+    fn truncate_by_chars() {
+        assert_eq!(truncate_str("Hello, World!", 5), "Hello...");
+        assert_eq!(truncate_str("你好，世界", 2), "你好...");
+    }
+}
+
+#[cfg(test)]
+mod test_sort_by_date {
+    use super::*;
+
+    fn items() -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("b", "2024-07-29T00:00:00+00:00"),
+            ("x", "not a date"),
+            ("c", "Tue, 30 Jul 2024 14:22:44 +0000"),
+            ("a", "2024-07-01T08:00:00+08:00"),
+        ]
+    }
+
+    #[test]
+    fn parse_rfc3339_and_rfc2822() {
+        assert!(parse_date("2024-07-29T15:38:27+00:00").is_some());
+        assert!(parse_date(" Tue, 07 May 2024 00:00:00 +0000 ").is_some());
+        assert!(parse_date("").is_none());
+    }
+
+    #[test]
+    fn oldest_first() {
+        let mut items = items();
+        sort_by_date(&mut items, PostOrder::OldestFirst, |v| v.1);
+        let names: Vec<_> = items.iter().map(|v| v.0).collect();
+        assert_eq!(names, vec!["a", "b", "c", "x"]);
+    }
+
+    #[test]
+    fn newest_first() {
+        let mut items = items();
+        sort_by_date(&mut items, PostOrder::NewestFirst, |v| v.1);
+        let names: Vec<_> = items.iter().map(|v| v.0).collect();
+        assert_eq!(names, vec!["c", "b", "a", "x"]);
+    }
+
+    #[test]
+    fn feed_order() {
+        let mut items = items();
+        sort_by_date(&mut items, PostOrder::FeedOrder, |v| v.1);
+        let names: Vec<_> = items.iter().map(|v| v.0).collect();
+        assert_eq!(names, vec!["b", "x", "c", "a"]);
+    }
+}
@@ -0,0 +1,254 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use tracing::error;
+use url::Url;
+
+use crate::conf::DingTalkWebhookConfig;
+
+use super::{delivery_result, Element, Message, Notifier, NotifyFuture};
+
+/// The response of DingTalk and WeCom robots.
+#[derive(Debug, Deserialize)]
+pub struct RobotResp {
+    pub errcode: i64,
+    #[serde(default)]
+    pub errmsg: String,
+}
+
+/// Posts a message to a DingTalk or WeCom robot. A response without the JSON
+/// of the robot API, e.g. the error page of a proxy, is an error as well.
+pub async fn post(client: &reqwest::Client, url: &str, req: &Value) -> anyhow::Result<()> {
+    // the url holds the access token of the robot, keep it out of the logs
+    let res = client
+        .post(url)
+        .json(req)
+        .send()
+        .await
+        .map_err(reqwest::Error::without_url)?;
+    let status = res.status();
+    let body = res.text().await.map_err(reqwest::Error::without_url)?;
+    match serde_json::from_str::<RobotResp>(&body) {
+        Ok(v) if v.errcode == 0 => Ok(()),
+        Ok(v) => Err(anyhow::anyhow!("code: {}, msg: {}", v.errcode, v.errmsg)),
+        Err(_) => Err(anyhow::anyhow!("status: {}, msg: {}", status, body)),
+    }
+}
+
+/// Delivers messages as markdown or actionCard messages to DingTalk robot webhooks.
+pub struct DingTalkNotifier {
+    client: reqwest::Client,
+    webhooks: Vec<DingTalkWebhookConfig>,
+}
+
+impl DingTalkNotifier {
+    pub fn new(webhooks: Vec<DingTalkWebhookConfig>) -> Self {
+        DingTalkNotifier {
+            client: reqwest::Client::new(),
+            webhooks,
+        }
+    }
+
+    async fn send(&self, webhook: &DingTalkWebhookConfig, req: &Value) -> anyhow::Result<()> {
+        let url = match &webhook.secret {
+            Some(secret) => {
+                signed_url(&webhook.url, secret, chrono::Utc::now().timestamp_millis())?
+            }
+            None => webhook.url.clone(),
+        };
+        post(&self.client, &url, req).await
+    }
+}
+
+impl Notifier for DingTalkNotifier {
+    fn notify<'a>(&'a self, msg: &'a Message) -> NotifyFuture<'a> {
+        Box::pin(async move {
+            let req = &render(msg);
+            // a failing robot does not keep the others from the message
+            let mut failed = 0;
+            for webhook in &self.webhooks {
+                if let Err(e) = self.send(webhook, req).await {
+                    error!("send {} to dingtalk failed: {}", msg.title, e);
+                    failed += 1;
+                }
+            }
+            delivery_result(failed, self.webhooks.len(), "dingtalk robots")
+        })
+    }
+}
+
+/// Signs `{timestamp}\n{secret}` with HMAC-SHA256, as robots with the
+/// "signature" security setting require.
+pub fn sign(secret: &str, timestamp: i64) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC can take a key of any size");
+    mac.update(format!("{}\n{}", timestamp, secret).as_bytes());
+    STANDARD.encode(mac.finalize().into_bytes())
+}
+
+/// Appends the `timestamp` (in milliseconds) and `sign` parameters to the webhook.
+pub fn signed_url(webhook: &str, secret: &str, timestamp: i64) -> anyhow::Result<String> {
+    let mut url = Url::parse(webhook)?;
+    url.query_pairs_mut()
+        .append_pair("timestamp", &timestamp.to_string())
+        .append_pair("sign", &sign(secret, timestamp));
+    Ok(url.to_string())
+}
+
+/// Renders the title, the date and the elements as DingTalk markdown, which
+/// only breaks lines on blank lines.
+fn markdown(msg: &Message) -> String {
+    let mut parts = vec![format!("### {}", msg.title)];
+    if let Some(date) = &msg.date {
+        parts.push(format!("> {}", date));
+    }
+    for element in &msg.elements {
+        match element {
+            Element::Markdown(content) => parts.extend(
+                content
+                    .lines()
+                    .map(str::trim_end)
+                    .filter(|v| !v.is_empty())
+                    .map(String::from),
+            ),
+            Element::Image { url, alt } => parts.push(format!("![{}]({})", alt, url)),
+            // rendered as the buttons of an actionCard
+            Element::Buttons(_) => {}
+        }
+    }
+    parts.join("\n\n")
+}
+
+/// Renders a message with buttons as an actionCard, a markdown message otherwise.
+pub fn render(msg: &Message) -> Value {
+    let buttons = msg
+        .elements
+        .iter()
+        .filter_map(|v| match v {
+            Element::Buttons(buttons) => Some(buttons),
+            _ => None,
+        })
+        .flatten()
+        .collect::<Vec<_>>();
+    if buttons.is_empty() {
+        return json!({
+            "msgtype": "markdown",
+            "markdown": {
+                "title": msg.title,
+                "text": markdown(msg),
+            }
+        });
+    }
+    json!({
+        "msgtype": "actionCard",
+        "actionCard": {
+            "title": msg.title,
+            "text": markdown(msg),
+            // buttons side by side
+            "btnOrientation": "1",
+            "btns": buttons
+                .iter()
+                .map(|v| json!({
+                    "title": v.text,
+                    "actionURL": v.url,
+                }))
+                .collect::<Vec<_>>(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, thread};
+
+    use crate::notifier::{test_server::serve, Color};
+
+    use super::*;
+
+    #[test]
+    fn test_signed_url() {
+        assert_eq!(
+            sign("SECxxx", 1721894400000),
+            "r35nt+sfAIiHu1quCTAGNxXjuq+zLkFZbowQR0SQTcg="
+        );
+        assert_eq!(
+            signed_url(
+                "https://oapi.dingtalk.com/robot/send?access_token=xxx",
+                "SECxxx",
+                1721894400000
+            )
+            .unwrap(),
+            "https://oapi.dingtalk.com/robot/send?access_token=xxx&timestamp=1721894400000&sign=r35nt%2BsfAIiHu1quCTAGNxXjuq%2BzLkFZbowQR0SQTcg%3D"
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let msg = Message::new("Announcing Rust 1.80", Color::Orange)
+            .date("2024-07-25T00:00:00+00:00")
+            .markdown("**Author**: The Rust Release Team\n\nsummary\n")
+            .button("origin link", "https://example.com/a");
+        assert_eq!(
+            render(&msg),
+            json!({
+                "msgtype": "actionCard",
+                "actionCard": {
+                    "title": "Announcing Rust 1.80",
+                    "text": "### Announcing Rust 1.80\n\n> 2024-07-25T00:00:00+00:00\n\n**Author**: The Rust Release Team\n\nsummary",
+                    "btnOrientation": "1",
+                    "btns": [{
+                        "title": "origin link",
+                        "actionURL": "https://example.com/a",
+                    }],
+                }
+            })
+        );
+
+        let msg = Message::new("Weekly", Color::Violet).markdown("content");
+        assert_eq!(
+            render(&msg),
+            json!({
+                "msgtype": "markdown",
+                "markdown": {
+                    "title": "Weekly",
+                    "text": "### Weekly\n\ncontent",
+                }
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_post() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            serve(
+                listener,
+                vec![
+                    (502, "<html>Bad Gateway</html>"),
+                    (
+                        200,
+                        r#"{"errcode":310000,"errmsg":"keywords not in content"}"#,
+                    ),
+                    (200, r#"{"errcode":0,"errmsg":"ok"}"#),
+                ],
+            )
+        });
+
+        let client = reqwest::Client::new();
+        let url = format!("http://127.0.0.1:{}/robot/send?access_token=xxx", port);
+        let req = json!({ "msgtype": "markdown" });
+        assert_eq!(
+            post(&client, &url, &req).await.unwrap_err().to_string(),
+            "status: 502 Bad Gateway, msg: <html>Bad Gateway</html>"
+        );
+        assert_eq!(
+            post(&client, &url, &req).await.unwrap_err().to_string(),
+            "code: 310000, msg: keywords not in content"
+        );
+        post(&client, &url, &req).await.unwrap();
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,201 @@
+use serde_json::{json, Value};
+use tracing::error;
+
+use crate::truncate_str;
+
+use super::{delivery_result, Color, Element, Message, Notifier, NotifyFuture};
+
+/// The max number of characters of the title of an embed.
+const MAX_TITLE_LEN: usize = 250;
+
+/// The max number of characters of the description of an embed.
+const MAX_DESCRIPTION_LEN: usize = 4000;
+
+/// Delivers messages as embeds to Discord webhooks.
+pub struct DiscordNotifier {
+    client: reqwest::Client,
+    webhooks: Vec<String>,
+}
+
+impl DiscordNotifier {
+    pub fn new(webhooks: Vec<String>) -> Self {
+        DiscordNotifier {
+            client: reqwest::Client::new(),
+            webhooks,
+        }
+    }
+
+    async fn send(&self, webhook: &str, req: &Value) -> anyhow::Result<()> {
+        // the url holds the token of the webhook, keep it out of the logs
+        let res = self
+            .client
+            .post(webhook)
+            .json(req)
+            .send()
+            .await
+            .map_err(reqwest::Error::without_url)?;
+        let status = res.status();
+        if !status.is_success() {
+            return Err(anyhow::anyhow!(
+                "status: {}, msg: {}",
+                status,
+                res.text().await.unwrap_or_default()
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Notifier for DiscordNotifier {
+    fn notify<'a>(&'a self, msg: &'a Message) -> NotifyFuture<'a> {
+        Box::pin(async move {
+            let req = &render(msg);
+            // a failing webhook does not keep the others from the message
+            let mut failed = 0;
+            for webhook in &self.webhooks {
+                if let Err(e) = self.send(webhook, req).await {
+                    error!("send {} to discord failed: {}", msg.title, e);
+                    failed += 1;
+                }
+            }
+            delivery_result(failed, self.webhooks.len(), "discord webhooks")
+        })
+    }
+}
+
+/// The color of an embed, the one of the Feishu card template.
+pub fn embed_color(color: Color) -> u32 {
+    match color {
+        Color::Blue => 0x3370ff,
+        Color::Wathet => 0x50cefb,
+        Color::Turquoise => 0x2dbeab,
+        Color::Green => 0x34c724,
+        Color::Yellow => 0xffc60a,
+        Color::Orange => 0xff8800,
+        Color::Red => 0xf54a45,
+        Color::Carmine => 0xe8386c,
+        Color::Violet => 0xd136d1,
+        Color::Purple => 0x7f3bf5,
+        Color::Indigo => 0x4954e6,
+        Color::Grey => 0x8f959e,
+    }
+}
+
+/// Renders the message as an embed. Webhooks cannot send buttons, the title
+/// links to the first one and the others are listed at the end.
+pub fn render(msg: &Message) -> Value {
+    let mut parts = vec![];
+    let mut image = None;
+    let mut links = vec![];
+    for element in &msg.elements {
+        match element {
+            Element::Markdown(content) => parts.push(content.trim().to_string()),
+            Element::Image { url, alt } => {
+                // an embed shows a single image
+                if image.is_none() {
+                    image = Some(url);
+                } else {
+                    parts.push(format!("[{}]({})", alt, url));
+                }
+            }
+            Element::Buttons(buttons) => links.extend(buttons),
+        }
+    }
+    if links.len() > 1 {
+        parts.push(
+            links
+                .iter()
+                .map(|v| format!("[{}]({})", v.text, v.url))
+                .collect::<Vec<_>>()
+                .join(" | "),
+        );
+    }
+    let mut embed = json!({
+        "title": truncate_str(&msg.title, MAX_TITLE_LEN),
+        "description": truncate_str(&parts.join("\n\n"), MAX_DESCRIPTION_LEN),
+        "color": embed_color(msg.color),
+    });
+    if let Some(button) = links.first() {
+        embed["url"] = json!(button.url);
+    }
+    if let Some(url) = image {
+        embed["image"] = json!({ "url": url });
+    }
+    if let Some(date) = &msg.date {
+        embed["footer"] = json!({ "text": date });
+    }
+    json!({ "embeds": [embed] })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, thread};
+
+    use crate::notifier::test_server::serve;
+
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let msg = Message::new("RustConf 2024", Color::Indigo)
+            .date("2024-09-18")
+            .image("https://example.com/a.jpg", "thumbnail")
+            .markdown("**Duration**: 1:04:37\n")
+            .button("watch", "https://example.com/a");
+        assert_eq!(
+            render(&msg),
+            json!({
+                "embeds": [{
+                    "title": "RustConf 2024",
+                    "description": "**Duration**: 1:04:37",
+                    "color": 0x4954e6,
+                    "url": "https://example.com/a",
+                    "image": { "url": "https://example.com/a.jpg" },
+                    "footer": { "text": "2024-09-18" },
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn test_render_links() {
+        let msg = Message::new("Go 1.22.5", Color::Blue)
+            .markdown("content")
+            .button("release notes", "https://go.dev/doc/devel/release")
+            .button("download", "https://go.dev/dl/");
+        let embed = &render(&msg)["embeds"][0];
+        assert_eq!(embed["url"], "https://go.dev/doc/devel/release");
+        assert_eq!(
+            embed["description"],
+            "content\n\n[release notes](https://go.dev/doc/devel/release) | [download](https://go.dev/dl/)"
+        );
+        assert!(embed.get("footer").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_notify_isolates_webhooks() {
+        // nothing listens on the port of a dropped listener
+        let unreachable = TcpListener::bind("127.0.0.1:0").unwrap();
+        let unreachable_port = unreachable.local_addr().unwrap().port();
+        drop(unreachable);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || serve(listener, vec![(204, "")]));
+
+        let notifier = DiscordNotifier::new(vec![
+            format!("http://127.0.0.1:{}/api/webhooks/1/token", unreachable_port),
+            format!("http://127.0.0.1:{}/api/webhooks/2/token", port),
+        ]);
+        let req = json!({ "content": "hi" });
+        let err = notifier
+            .send(&notifier.webhooks[0], &req)
+            .await
+            .unwrap_err();
+        assert!(!format!("{:?}", err).contains("token"));
+
+        let msg = Message::new("Go 1.22.5", Color::Blue).markdown("content");
+        let err = notifier.notify(&msg).await.unwrap_err();
+        assert_eq!(err.to_string(), "1 of 2 discord webhooks failed");
+        assert_eq!(server.join().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,352 @@
+//! The email digest: channels queue their messages in Redis and a job of its
+//! own schedule sends them in a single email, grouped by channel.
+
+use std::sync::LazyLock;
+
+use lettre::{
+    message::MultiPart, transport::smtp::authentication::Credentials, Message as Email,
+    SmtpTransport, Transport,
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tracing::{error, info};
+
+use crate::{
+    conf::{EmailDigestConfig, SmtpConfig, SmtpTls},
+    redis_base::Redis,
+};
+
+use super::{Element, Message, Notifier, NotifyFuture};
+
+/// A queued message and the channel that pushed it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DigestItem {
+    pub channel: String,
+    pub message: Message,
+}
+
+/// Queues messages for the next email digest.
+pub struct EmailDigestNotifier {
+    redis: Redis,
+    channel: String,
+}
+
+impl EmailDigestNotifier {
+    pub fn new(redis: Redis, channel: &str) -> Self {
+        EmailDigestNotifier {
+            redis,
+            channel: channel.to_string(),
+        }
+    }
+}
+
+impl Notifier for EmailDigestNotifier {
+    fn notify<'a>(&'a self, msg: &'a Message) -> NotifyFuture<'a> {
+        Box::pin(async move {
+            let item = DigestItem {
+                channel: self.channel.clone(),
+                message: msg.clone(),
+            };
+            self.redis
+                .rpush(Redis::LIST_EMAIL_DIGEST_KEY, &serde_json::to_string(&item)?)
+        })
+    }
+}
+
+/// Sends the queued messages, which are only removed from the queue once sent.
+/// Blocks on Redis and SMTP, async callers run it with `spawn_blocking`.
+pub fn send_digest(redis: &Redis, conf: &EmailDigestConfig) -> anyhow::Result<()> {
+    let values = redis.lrange_all(Redis::LIST_EMAIL_DIGEST_KEY)?;
+    let items = values
+        .iter()
+        .filter_map(|v| {
+            serde_json::from_str::<DigestItem>(v)
+                .map_err(|e| error!("parse digest item {} failed: {}", v, e))
+                .ok()
+        })
+        .collect::<Vec<_>>();
+    if items.is_empty() {
+        info!("no message for the email digest");
+    } else {
+        send_email(&transport(&conf.smtp)?, conf, &items)?;
+        info!("send email digest of {} messages success", items.len());
+    }
+    redis.ltrim_front(Redis::LIST_EMAIL_DIGEST_KEY, values.len())
+}
+
+fn transport(conf: &SmtpConfig) -> anyhow::Result<SmtpTransport> {
+    let builder = match conf.tls {
+        SmtpTls::None => SmtpTransport::builder_dangerous(&conf.host),
+        SmtpTls::StartTls => SmtpTransport::starttls_relay(&conf.host)?,
+        SmtpTls::Tls => SmtpTransport::relay(&conf.host)?,
+    }
+    .port(conf.port);
+    let builder = if conf.username.is_empty() {
+        builder
+    } else {
+        builder.credentials(Credentials::new(
+            conf.username.clone(),
+            conf.password.clone(),
+        ))
+    };
+    Ok(builder.build())
+}
+
+fn send_email(
+    transport: &SmtpTransport,
+    conf: &EmailDigestConfig,
+    items: &[DigestItem],
+) -> anyhow::Result<()> {
+    let mut builder = Email::builder()
+        .from(conf.from.parse()?)
+        .subject(conf.subject.as_str());
+    for recipient in &conf.recipients {
+        builder = builder.to(recipient.parse()?);
+    }
+    let groups = group_by_channel(items);
+    let email = builder.multipart(MultiPart::alternative_plain_html(
+        render_text(&groups),
+        render_html(&groups),
+    ))?;
+    transport.send(&email)?;
+    Ok(())
+}
+
+/// Groups the messages by channel, in the order the channels first pushed.
+fn group_by_channel(items: &[DigestItem]) -> Vec<(&str, Vec<&Message>)> {
+    let mut groups: Vec<(&str, Vec<&Message>)> = vec![];
+    for item in items {
+        match groups.iter_mut().find(|(v, _)| *v == item.channel) {
+            Some((_, messages)) => messages.push(&item.message),
+            None => groups.push((&item.channel, vec![&item.message])),
+        }
+    }
+    groups
+}
+
+fn render_text(groups: &[(&str, Vec<&Message>)]) -> String {
+    let mut res = String::with_capacity(4096);
+    for (channel, messages) in groups {
+        res.push_str(&format!("== {} ==\n\n", channel));
+        for msg in messages {
+            res.push_str(&msg.title);
+            res.push('\n');
+            if let Some(date) = &msg.date {
+                res.push_str(&format!("-- {}\n", date));
+            }
+            for element in &msg.elements {
+                match element {
+                    Element::Markdown(content) => {
+                        res.push_str(content.trim());
+                        res.push('\n');
+                    }
+                    Element::Image { url, alt } => res.push_str(&format!("{}: {}\n", alt, url)),
+                    Element::Buttons(buttons) => {
+                        for button in buttons {
+                            res.push_str(&format!("{}: {}\n", button.text, button.url));
+                        }
+                    }
+                }
+            }
+            res.push('\n');
+        }
+    }
+    res
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+static LINK_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[([^\]]*)\]\(([^)\s]+)\)").unwrap());
+static BOLD_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\*\*(.+?)\*\*").unwrap());
+
+/// Converts the markdown of the channels to HTML.
+fn markdown_html(s: &str) -> String {
+    let s = escape_html(s.trim());
+    let s = LINK_RE.replace_all(&s, r#"<a href="$2">$1</a>"#);
+    let s = BOLD_RE.replace_all(&s, "<strong>$1</strong>");
+    s.lines()
+        .map(|v| if v.trim() == "---" { "<hr>" } else { v })
+        .collect::<Vec<_>>()
+        .join("<br>\n")
+}
+
+fn render_html(groups: &[(&str, Vec<&Message>)]) -> String {
+    let mut res = String::with_capacity(8192);
+    res.push_str("<html><body>\n");
+    for (channel, messages) in groups {
+        res.push_str(&format!("<h2>{}</h2>\n", escape_html(channel)));
+        for msg in messages {
+            res.push_str(&format!("<h3>{}</h3>\n", escape_html(&msg.title)));
+            if let Some(date) = &msg.date {
+                res.push_str(&format!("<p><em>{}</em></p>\n", escape_html(date)));
+            }
+            for element in &msg.elements {
+                match element {
+                    Element::Markdown(content) => {
+                        res.push_str(&format!("<p>{}</p>\n", markdown_html(content)))
+                    }
+                    Element::Image { url, alt } => res.push_str(&format!(
+                        "<p><img src=\"{}\" alt=\"{}\" style=\"max-width: 100%\"></p>\n",
+                        escape_html(url),
+                        escape_html(alt)
+                    )),
+                    Element::Buttons(buttons) => res.push_str(&format!(
+                        "<p>{}</p>\n",
+                        buttons
+                            .iter()
+                            .map(|v| format!(
+                                "<a href=\"{}\">{}</a>",
+                                escape_html(&v.url),
+                                escape_html(&v.text)
+                            ))
+                            .collect::<Vec<_>>()
+                            .join(" | ")
+                    )),
+                }
+            }
+        }
+    }
+    res.push_str("</body></html>\n");
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::{TcpListener, TcpStream},
+        thread,
+    };
+
+    use crate::notifier::Color;
+
+    use super::*;
+
+    fn items() -> Vec<DigestItem> {
+        vec![
+            DigestItem {
+                channel: "Rust Blog".to_string(),
+                message: Message::new("Announcing Rust 1.80", Color::Orange)
+                    .date("2024-07-25")
+                    .markdown("**Author**: The Rust Release Team")
+                    .button("origin link", "https://blog.rust-lang.org/a"),
+            },
+            DigestItem {
+                channel: "Go Blog".to_string(),
+                message: Message::new("Go 1.23 is released", Color::Blue)
+                    .markdown("See the [notes](https://go.dev/doc/go1.23)."),
+            },
+            DigestItem {
+                channel: "Rust Blog".to_string(),
+                message: Message::new("Rust 1.80.1", Color::Orange).markdown("a & b"),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_group_by_channel() {
+        let items = items();
+        let groups = group_by_channel(&items);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].0, "Rust Blog");
+        assert_eq!(groups[0].1.len(), 2);
+        assert_eq!(groups[1].0, "Go Blog");
+    }
+
+    #[test]
+    fn test_render_text() {
+        let items = items();
+        assert_eq!(
+            render_text(&group_by_channel(&items)),
+            "== Rust Blog ==\n\n\
+             Announcing Rust 1.80\n-- 2024-07-25\n**Author**: The Rust Release Team\norigin link: https://blog.rust-lang.org/a\n\n\
+             Rust 1.80.1\na & b\n\n\
+             == Go Blog ==\n\n\
+             Go 1.23 is released\nSee the [notes](https://go.dev/doc/go1.23).\n\n"
+        );
+    }
+
+    #[test]
+    fn test_markdown_html() {
+        assert_eq!(
+            markdown_html("**Author**: <Go> & [notes](https://go.dev/a?b=1&c=2)\n---\nend\n"),
+            "<strong>Author</strong>: &lt;Go&gt; &amp; <a href=\"https://go.dev/a?b=1&amp;c=2\">notes</a><br>\n<hr><br>\nend"
+        );
+    }
+
+    /// A local SMTP stand-in accepting a single message, returns its data.
+    fn serve(listener: TcpListener) -> String {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut writer: TcpStream = stream;
+        writer.write_all(b"220 localhost ESMTP\r\n").unwrap();
+        let mut data = String::new();
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line).unwrap() == 0 {
+                break;
+            }
+            let command = line.trim_end().to_uppercase();
+            if command.starts_with("EHLO") {
+                writer
+                    .write_all(b"250-localhost\r\n250 8BITMIME\r\n")
+                    .unwrap();
+            } else if command == "DATA" {
+                writer.write_all(b"354 end data with .\r\n").unwrap();
+                loop {
+                    line.clear();
+                    reader.read_line(&mut line).unwrap();
+                    if line == ".\r\n" {
+                        break;
+                    }
+                    data.push_str(&line);
+                }
+                writer.write_all(b"250 ok\r\n").unwrap();
+            } else if command == "QUIT" {
+                writer.write_all(b"221 bye\r\n").unwrap();
+                break;
+            } else {
+                writer.write_all(b"250 ok\r\n").unwrap();
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn test_send_email() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || serve(listener));
+
+        let conf = EmailDigestConfig {
+            cron_expression: "0 0 9 * * * *".to_string(),
+            smtp: SmtpConfig {
+                host: "127.0.0.1".to_string(),
+                port,
+                username: String::new(),
+                password: String::new(),
+                tls: SmtpTls::None,
+            },
+            from: "Hedon Bot <hedon-bot@example.com>".to_string(),
+            recipients: vec!["dev@example.com".to_string()],
+            subject: "Hedon Bot digest".to_string(),
+        };
+        let transport = transport(&conf.smtp).unwrap();
+        send_email(&transport, &conf, &items()).unwrap();
+        drop(transport);
+
+        let data = server.join().unwrap();
+        assert!(data.contains("Subject: Hedon Bot digest"));
+        assert!(data.contains("To: dev@example.com"));
+        assert!(data.contains("multipart/alternative"));
+        assert!(data.contains("== Rust Blog =="));
+        assert!(data.contains("<h2>Go Blog</h2>"));
+    }
+}
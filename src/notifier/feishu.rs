@@ -0,0 +1,509 @@
+use std::{sync::Mutex, time::Duration};
+
+use serde::Serialize;
+use tracing::{error, warn};
+
+use crate::{
+    conf::{FeishuAppConfig, FeishuWebhookConfig},
+    feishu_bot::{
+        self, Card, CardElement, CardMessage, FailureKind, SendFailure, SendMessageResp, Template,
+        TenantAccessToken,
+    },
+    truncate_str,
+};
+
+/// The number of retries of a message rejected for a transient reason.
+const MAX_RETRIES: u32 = 3;
+
+/// The max size of the request body of a custom robot.
+const MAX_REQUEST_BYTES: usize = 20 * 1024;
+
+/// The room kept for the `timestamp` and `sign` of a signed request.
+const SIGNATURE_BYTES: usize = 128;
+
+use super::{delivery_result, Color, Element, Message, Notifier, NotifyFuture};
+
+/// Delivers messages as interactive cards to Feishu custom robot webhooks.
+/// Failures are logged per webhook, after retries for the transient ones.
+pub struct FeishuNotifier {
+    client: reqwest::Client,
+    webhooks: Vec<FeishuWebhookConfig>,
+    app: Option<FeishuAppConfig>,
+    // the first delay between retries, doubled on each one
+    retry_delay: Duration,
+    // reused by the image uploads until it expires
+    token: Mutex<Option<TenantAccessToken>>,
+}
+
+impl FeishuNotifier {
+    pub fn new(webhooks: Vec<FeishuWebhookConfig>, app: Option<FeishuAppConfig>) -> Self {
+        FeishuNotifier {
+            client: reqwest::Client::new(),
+            webhooks,
+            app,
+            retry_delay: Duration::from_secs(1),
+            token: Mutex::new(None),
+        }
+    }
+
+    async fn send(
+        &self,
+        webhook: &FeishuWebhookConfig,
+        card: &CardMessage,
+    ) -> Result<(), SendFailure> {
+        let req = match &webhook.secret {
+            Some(secret) => card.clone().sign(secret, chrono::Utc::now().timestamp()),
+            None => card.clone(),
+        };
+        let res = self
+            .client
+            .post(&webhook.url)
+            .json(&req)
+            .send()
+            .await
+            .map_err(|e| SendFailure {
+                kind: FailureKind::Unavailable,
+                code: 0,
+                // the url holds the token of the robot, keep it out of the logs
+                msg: e.without_url().to_string(),
+            })?;
+        let status = res.status().as_u16();
+        let body = res.text().await.unwrap_or_default();
+        match serde_json::from_str::<SendMessageResp>(&body) {
+            Ok(v) => match v.failure() {
+                Some(failure) => Err(failure),
+                None => Ok(()),
+            },
+            Err(_) => Err(SendFailure::from_status(status, &body)),
+        }
+    }
+
+    /// Sends the card, retrying with an exponential backoff while it is
+    /// rejected for a transient reason.
+    async fn send_with_retry(
+        &self,
+        webhook: &FeishuWebhookConfig,
+        card: &CardMessage,
+    ) -> Result<(), SendFailure> {
+        let mut attempt = 0;
+        loop {
+            match self.send(webhook, card).await {
+                Err(failure) if failure.is_transient() && attempt < MAX_RETRIES => {
+                    warn!("send to feishu failed: {}, retrying", failure);
+                    tokio::time::sleep(self.retry_delay * 2u32.pow(attempt)).await;
+                    attempt += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    /// Returns the cached tenant access token, or gets a new one when it
+    /// has expired.
+    async fn access_token(&self, app: &FeishuAppConfig) -> anyhow::Result<String> {
+        let cached = self
+            .token
+            .lock()
+            .unwrap()
+            .as_ref()
+            .filter(|v| v.is_valid())
+            .map(|v| v.token.clone());
+        if let Some(token) = cached {
+            return Ok(token);
+        }
+        let token = feishu_bot::tenant_access_token(&self.client, app).await?;
+        let res = token.token.clone();
+        *self.token.lock().unwrap() = Some(token);
+        Ok(res)
+    }
+
+    /// Uploads the images of the message, webhook robots cannot show remote
+    /// images. An image that fails to upload is linked instead.
+    async fn upload_images(&self, msg: &Message) -> Vec<Option<String>> {
+        let mut keys = vec![];
+        for element in &msg.elements {
+            let key = match (element, &self.app) {
+                (Element::Image { url, .. }, Some(app)) => {
+                    let key = match self.access_token(app).await {
+                        Ok(token) => feishu_bot::upload_image(&self.client, &token, url).await,
+                        Err(e) => Err(e),
+                    };
+                    match key {
+                        Ok(v) => Some(v),
+                        Err(e) => {
+                            error!("upload image {} failed: {}", url, e);
+                            None
+                        }
+                    }
+                }
+                _ => None,
+            };
+            keys.push(key);
+        }
+        keys
+    }
+}
+
+impl Notifier for FeishuNotifier {
+    fn notify<'a>(&'a self, msg: &'a Message) -> NotifyFuture<'a> {
+        Box::pin(async move {
+            let image_keys = self.upload_images(msg).await;
+            let cards = render(msg, &image_keys);
+            // a failing webhook does not keep the others from the message
+            let mut failed = 0;
+            for webhook in &self.webhooks {
+                let mut ok = true;
+                for card in &cards {
+                    if let Err(failure) = self.send_with_retry(webhook, card).await {
+                        error!("send {} to feishu failed: {}", msg.title, failure);
+                        ok = false;
+                    }
+                }
+                if !ok {
+                    failed += 1;
+                }
+            }
+            delivery_result(failed, self.webhooks.len(), "feishu webhooks")
+        })
+    }
+}
+
+pub fn template(color: Color) -> Template {
+    match color {
+        Color::Blue => Template::Blue,
+        Color::Wathet => Template::Wathet,
+        Color::Turquoise => Template::Turquoise,
+        Color::Green => Template::Green,
+        Color::Yellow => Template::Yellow,
+        Color::Orange => Template::Orange,
+        Color::Red => Template::Red,
+        Color::Carmine => Template::Carmine,
+        Color::Violet => Template::Violet,
+        Color::Purple => Template::Purple,
+        Color::Indigo => Template::Indigo,
+        Color::Grey => Template::Grey,
+    }
+}
+
+/// Renders the message as interactive cards, `image_keys` holds the
+/// uploaded key of each element, if any. A message over the size limit of a
+/// request is split across several cards titled `(1/2)`, `(2/2)`...
+pub fn render(msg: &Message, image_keys: &[Option<String>]) -> Vec<CardMessage> {
+    render_with_limit(msg, image_keys, MAX_REQUEST_BYTES - SIGNATURE_BYTES)
+}
+
+fn render_with_limit(
+    msg: &Message,
+    image_keys: &[Option<String>],
+    max_bytes: usize,
+) -> Vec<CardMessage> {
+    let mut card = Card::new(card_title(msg, None), template(msg.color));
+    for (i, element) in msg.elements.iter().enumerate() {
+        card = match element {
+            Element::Markdown(content) => card.markdown(content.as_str()),
+            Element::Image { url, alt } => match image_keys.get(i).cloned().flatten() {
+                Some(key) => card.image(key, alt.as_str()),
+                None => card.markdown(format!("[{}]({})", alt, url)),
+            },
+            // the buttons of a message are grouped in a single element
+            Element::Buttons(buttons) => buttons
+                .iter()
+                .fold(card, |card, v| card.button(v.text.as_str(), v.url.as_str())),
+        };
+    }
+    let card = CardMessage::new(card);
+    if json_len(&card) <= max_bytes {
+        return vec![card];
+    }
+
+    // the room left for the elements of a card titled with the largest part number
+    let empty = CardMessage::new(Card::new(
+        card_title(msg, Some((99, 99))),
+        template(msg.color),
+    ));
+    let budget = max_bytes.saturating_sub(json_len(&empty));
+    let mut parts: Vec<Vec<CardElement>> = vec![vec![]];
+    let mut size = 0;
+    for element in card
+        .card
+        .elements
+        .into_iter()
+        .flat_map(|v| split_element(v, budget))
+    {
+        // elements are separated by a comma
+        let len = json_len(&element) + 1;
+        if size + len > budget && !parts.last().unwrap().is_empty() {
+            parts.push(vec![]);
+            size = 0;
+        }
+        parts.last_mut().unwrap().push(element);
+        size += len;
+    }
+    let n = parts.len();
+    parts
+        .into_iter()
+        .enumerate()
+        .map(|(i, elements)| {
+            let mut card = Card::new(card_title(msg, Some((i + 1, n))), template(msg.color));
+            card.elements = elements;
+            CardMessage::new(card)
+        })
+        .collect()
+}
+
+fn card_title(msg: &Message, part: Option<(usize, usize)>) -> String {
+    let title = match part {
+        Some((i, n)) => format!("{} ({}/{})", msg.title, i, n),
+        None => msg.title.clone(),
+    };
+    match &msg.date {
+        Some(date) => format!("{} \n           -- {}", title, date),
+        None => title,
+    }
+}
+
+fn json_len<T: Serialize>(v: &T) -> usize {
+    serde_json::to_string(v).map(|v| v.len()).unwrap_or(0)
+}
+
+/// Splits a markdown element over the budget by lines, truncating the lines
+/// that are over it on their own.
+fn split_element(element: CardElement, budget: usize) -> Vec<CardElement> {
+    let markdown = |content: String| CardElement::Markdown { content };
+    let content = match element {
+        CardElement::Markdown { content } => content,
+        _ => return vec![element],
+    };
+    let overhead = json_len(&markdown(String::new()));
+    if json_len(&content) + overhead <= budget {
+        return vec![markdown(content)];
+    }
+    let mut res = vec![];
+    let mut chunk = String::new();
+    for line in content.lines() {
+        // a char takes at most 6 bytes once escaped
+        let line = if json_len(&line) + overhead > budget {
+            truncate_str(line, budget.saturating_sub(overhead) / 6)
+        } else {
+            line.to_string()
+        };
+        if !chunk.is_empty() && json_len(&format!("{}\n{}", chunk, line)) + overhead > budget {
+            res.push(markdown(std::mem::take(&mut chunk)));
+        }
+        if !chunk.is_empty() {
+            chunk.push('\n');
+        }
+        chunk.push_str(&line);
+    }
+    if !chunk.is_empty() {
+        res.push(markdown(chunk));
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, thread, time::Instant};
+
+    use serde_json::json;
+
+    use crate::notifier::test_server::serve;
+
+    use super::*;
+
+    fn notifier(port: u16, paths: &[&str]) -> FeishuNotifier {
+        FeishuNotifier {
+            retry_delay: Duration::from_millis(10),
+            ..FeishuNotifier::new(
+                paths
+                    .iter()
+                    .map(|v| format!("http://127.0.0.1:{}/{}", port, v).as_str().into())
+                    .collect(),
+                None,
+            )
+        }
+    }
+
+    #[tokio::test]
+    async fn test_access_token_is_cached() {
+        let notifier = notifier(0, &[]);
+        *notifier.token.lock().unwrap() = Some(TenantAccessToken {
+            token: "t-cached".to_string(),
+            expires_at: Instant::now() + Duration::from_secs(60),
+        });
+        let app = FeishuAppConfig {
+            app_id: "cli_xxx".to_string(),
+            app_secret: "xxx".to_string(),
+        };
+        // no request is sent for a valid token
+        assert_eq!(notifier.access_token(&app).await.unwrap(), "t-cached");
+    }
+
+    #[tokio::test]
+    async fn test_notify_retry() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            serve(
+                listener,
+                vec![
+                    (200, r#"{"code":11232,"msg":"frequency limited"}"#),
+                    (502, "Bad Gateway"),
+                    (
+                        200,
+                        r#"{"StatusCode":0,"StatusMessage":"success","code":0}"#,
+                    ),
+                ],
+            )
+        });
+        let msg = Message::new("Announcing Rust 1.80", Color::Orange).markdown("content");
+        notifier(port, &["a"]).notify(&msg).await.unwrap();
+        assert_eq!(server.join().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_notify_isolation() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            serve(
+                listener,
+                vec![
+                    (200, r#"{"code":19024,"msg":"Key Words Not Found"}"#),
+                    (200, "not json"),
+                    (200, r#"{"code":0,"msg":"success"}"#),
+                ],
+            )
+        });
+        let msg = Message::new("Announcing Rust 1.80", Color::Orange).markdown("content");
+        // the permanent failures are not retried and the last webhook still gets the card
+        let err = notifier(port, &["a", "b", "c"])
+            .notify(&msg)
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "2 of 3 feishu webhooks failed");
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[2].1.contains("Announcing Rust 1.80"));
+    }
+
+    #[test]
+    fn test_render() {
+        let msg = Message::new("Announcing Rust 1.80", Color::Orange)
+            .date("2024-07-25T00:00:00+00:00")
+            .markdown("content")
+            .button("origin link", "https://example.com/a");
+        assert_eq!(
+            serde_json::to_value(&render(&msg, &[])[0]).unwrap(),
+            json!({
+                "msg_type": "interactive",
+                "card": {
+                    "elements": [
+                        {
+                            "tag": "markdown",
+                            "content": "content",
+                        },
+                        {
+                            "actions": [{
+                                "tag": "button",
+                                "text": {
+                                    "content": "origin link",
+                                    "tag": "lark_md"
+                                },
+                                "url": "https://example.com/a",
+                                "type": "default",
+                                "value": {}
+                            }],
+                            "tag": "action"
+                        }
+                    ],
+                    "header": {
+                        "title": {
+                            "content": "Announcing Rust 1.80 \n           -- 2024-07-25T00:00:00+00:00",
+                            "tag": "plain_text"
+                        },
+                        "template": "orange",
+                    }
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_render_image() {
+        let msg = Message::new("RustConf", Color::Indigo)
+            .image("https://example.com/a.jpg", "thumbnail")
+            .image("https://example.com/b.jpg", "thumbnail");
+        let card = serde_json::to_value(&render(&msg, &[Some("img_v3_xxx".to_string()), None])[0])
+            .unwrap();
+        let elements = &card["card"]["elements"];
+        assert_eq!(elements[0]["tag"], "img");
+        assert_eq!(elements[0]["img_key"], "img_v3_xxx");
+        assert_eq!(elements[1]["tag"], "markdown");
+        assert_eq!(
+            elements[1]["content"],
+            "[thumbnail](https://example.com/b.jpg)"
+        );
+    }
+
+    fn contents(cards: &[CardMessage]) -> Vec<String> {
+        cards
+            .iter()
+            .flat_map(|v| v.card.elements.iter())
+            .filter_map(|v| match v {
+                CardElement::Markdown { content } => Some(content.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_render_split() {
+        let articles = (0..3)
+            .map(|i| format!("**Article {}**: {}", i, "a".repeat(300)))
+            .collect::<Vec<_>>();
+        let msg = articles
+            .iter()
+            .fold(
+                Message::new("Golang Weekly", Color::Blue).date("2024-07-25"),
+                |msg, v| msg.markdown(v.as_str()),
+            )
+            .button("origin link", "https://example.com/a");
+        let cards = render_with_limit(&msg, &[], 1000);
+        assert_eq!(cards.len(), 2);
+        for card in &cards {
+            assert!(json_len(card) <= 1000);
+        }
+        assert_eq!(
+            serde_json::to_value(&cards[0].card.header.title).unwrap()["content"],
+            "Golang Weekly (1/2) \n           -- 2024-07-25"
+        );
+        assert_eq!(contents(&cards), articles);
+        assert!(matches!(
+            cards[1].card.elements.last(),
+            Some(CardElement::Action { .. })
+        ));
+        assert_eq!(render_with_limit(&msg, &[], 2000).len(), 1);
+    }
+
+    #[test]
+    fn test_render_split_markdown() {
+        let lines = (0..50)
+            .map(|i| format!("{:02} {}", i, "b".repeat(40)))
+            .collect::<Vec<_>>();
+        let msg = Message::new("Golang Weekly", Color::Blue).markdown(lines.join("\n"));
+        let cards = render_with_limit(&msg, &[], 1000);
+        assert!(cards.len() > 1);
+        for card in &cards {
+            assert!(json_len(card) <= 1000);
+        }
+        assert_eq!(contents(&cards).join("\n"), lines.join("\n"));
+
+        // a line over the limit on its own is truncated
+        let msg = Message::new("Golang Weekly", Color::Blue).markdown("c".repeat(5000));
+        let cards = render_with_limit(&msg, &[], 1000);
+        assert_eq!(cards.len(), 1);
+        assert!(json_len(&cards[0]) <= 1000);
+        assert!(contents(&cards)[0].ends_with("c..."));
+    }
+}
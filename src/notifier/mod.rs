@@ -0,0 +1,380 @@
+//! Delivery of the messages built by the channels.
+//!
+//! Channels render a platform-neutral [`Message`] and hand it to the
+//! notifiers of their [`ArticleSourceConfig`], each of which translates it
+//! for its own platform.
+
+use std::{future::Future, pin::Pin};
+
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{
+    conf::{ArticleSourceConfig, FeishuAppConfig},
+    redis_base::Redis,
+};
+
+mod dingtalk;
+mod discord;
+pub mod email;
+mod feishu;
+mod slack;
+mod telegram;
+mod webhook;
+mod wecom;
+
+pub use dingtalk::DingTalkNotifier;
+pub use discord::DiscordNotifier;
+pub use email::EmailDigestNotifier;
+pub use feishu::FeishuNotifier;
+pub use slack::SlackNotifier;
+pub use telegram::TelegramNotifier;
+pub use webhook::WebhookNotifier;
+pub use wecom::WeComNotifier;
+
+/// The header color of a message, named after the Feishu card templates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Color {
+    Blue,
+    Wathet,
+    Turquoise,
+    Green,
+    Yellow,
+    Orange,
+    Red,
+    Carmine,
+    Violet,
+    Purple,
+    Indigo,
+    Grey,
+}
+
+/// A link rendered as a button.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Button {
+    pub text: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Element {
+    /// Text with `**bold**` and `[text](url)` markdown.
+    Markdown(String),
+    /// A remote image, shown as a link where it cannot be displayed.
+    Image {
+        url: String,
+        alt: String,
+    },
+    Buttons(Vec<Button>),
+}
+
+/// A message as built by the channels, before any platform formatting.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Message {
+    pub title: String,
+    // shown after the title, e.g. the publication date of an article
+    pub date: Option<String>,
+    pub color: Color,
+    pub elements: Vec<Element>,
+}
+
+impl Message {
+    pub fn new(title: impl Into<String>, color: Color) -> Self {
+        Message {
+            title: title.into(),
+            date: None,
+            color,
+            elements: vec![],
+        }
+    }
+
+    /// Sets the date shown after the title, an empty date is ignored.
+    pub fn date(mut self, date: impl Into<String>) -> Self {
+        let date = date.into();
+        if !date.is_empty() {
+            self.date = Some(date);
+        }
+        self
+    }
+
+    pub fn markdown(mut self, content: impl Into<String>) -> Self {
+        self.elements.push(Element::Markdown(content.into()));
+        self
+    }
+
+    pub fn image(mut self, url: impl Into<String>, alt: impl Into<String>) -> Self {
+        self.elements.push(Element::Image {
+            url: url.into(),
+            alt: alt.into(),
+        });
+        self
+    }
+
+    /// Adds a button, next to the previous one if the message ends with buttons.
+    pub fn button(mut self, text: impl Into<String>, url: impl Into<String>) -> Self {
+        let button = Button {
+            text: text.into(),
+            url: url.into(),
+        };
+        match self.elements.last_mut() {
+            Some(Element::Buttons(buttons)) => buttons.push(button),
+            _ => self.elements.push(Element::Buttons(vec![button])),
+        }
+        self
+    }
+
+    /// The compact message pushed when an already pushed article is updated.
+    pub fn updated(title: &str, updated: &str, url: &str) -> Self {
+        Message::new(format!("[Updated] {}", title), Color::Grey)
+            .markdown(format!("**{}** has been updated at {}.", title, updated))
+            .button("origin link", url)
+    }
+}
+
+/// The future of a delivery, `Sync` as the cron jobs awaiting it must be.
+pub type NotifyFuture<'a> = Pin<Box<dyn Future<Output = anyhow::Result<()>> + Send + Sync + 'a>>;
+
+pub trait Notifier: Send + Sync {
+    /// Delivers the message to every target of the notifier, a failing one
+    /// doesn't keep the others from it. Each failure is logged, the error
+    /// only counts them.
+    fn notify<'a>(&'a self, msg: &'a Message) -> NotifyFuture<'a>;
+}
+
+/// The result of a delivery to `total` targets, `failed` of which failed.
+fn delivery_result(failed: usize, total: usize, targets: &str) -> anyhow::Result<()> {
+    if failed > 0 {
+        return Err(anyhow::anyhow!(
+            "{} of {} {} failed",
+            failed,
+            total,
+            targets
+        ));
+    }
+    Ok(())
+}
+
+/// Builds the notifiers of a channel. `channel` names it in the email digest,
+/// `feishu_app` lets Feishu cards show images.
+pub fn from_source(
+    redis: &Redis,
+    channel: &str,
+    source: &ArticleSourceConfig,
+    feishu_app: Option<&FeishuAppConfig>,
+) -> Vec<Box<dyn Notifier>> {
+    let mut notifiers: Vec<Box<dyn Notifier>> = vec![];
+    if !source.webhooks.is_empty() {
+        notifiers.push(Box::new(FeishuNotifier::new(
+            source.webhooks.clone(),
+            feishu_app.cloned(),
+        )));
+    }
+    if !source.slack_webhooks.is_empty() {
+        notifiers.push(Box::new(SlackNotifier::new(source.slack_webhooks.clone())));
+    }
+    if !source.dingtalk_webhooks.is_empty() {
+        notifiers.push(Box::new(DingTalkNotifier::new(
+            source.dingtalk_webhooks.clone(),
+        )));
+    }
+    if !source.wecom_webhooks.is_empty() {
+        notifiers.push(Box::new(WeComNotifier::new(source.wecom_webhooks.clone())));
+    }
+    if !source.telegram_chats.is_empty() {
+        notifiers.push(Box::new(TelegramNotifier::new(
+            source.telegram_chats.clone(),
+        )));
+    }
+    if !source.discord_webhooks.is_empty() {
+        notifiers.push(Box::new(DiscordNotifier::new(
+            source.discord_webhooks.clone(),
+        )));
+    }
+    if !source.http_webhooks.is_empty() {
+        notifiers.push(Box::new(WebhookNotifier::new(
+            channel,
+            source.http_webhooks.clone(),
+        )));
+    }
+    if source.email_digest {
+        notifiers.push(Box::new(EmailDigestNotifier::new(redis.clone(), channel)));
+    }
+    notifiers
+}
+
+/// Delivers the message with every notifier, a failing one doesn't keep the
+/// others from delivering. Each failure is logged, the error only counts them.
+pub async fn notify_all(notifiers: &[Box<dyn Notifier>], msg: &Message) -> anyhow::Result<()> {
+    let mut failed = 0;
+    for notifier in notifiers {
+        if let Err(e) = notifier.notify(msg).await {
+            error!("notify {} failed: {:#}", msg.title, e);
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        return Err(anyhow::anyhow!(
+            "{} of {} notifiers failed to deliver {}",
+            failed,
+            notifiers.len(),
+            msg.title
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod test_server {
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+    };
+
+    /// A local HTTP stand-in answering a request with each status and body in
+    /// order, returns the headers and body of each request.
+    pub fn serve(listener: TcpListener, responses: Vec<(u16, &str)>) -> Vec<(Vec<String>, String)> {
+        let mut requests = vec![];
+        for (status, body) in responses {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut headers = vec![];
+            let mut line = String::new();
+            loop {
+                line.clear();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                headers.push(line.trim_end().to_lowercase());
+            }
+            let len = headers
+                .iter()
+                .find_map(|v| v.strip_prefix("content-length: "))
+                .map(|v| v.parse::<usize>().unwrap())
+                .unwrap_or(0);
+            let mut req_body = vec![0; len];
+            reader.read_exact(&mut req_body).unwrap();
+            let mut writer = stream;
+            writer
+                .write_all(
+                    format!(
+                        "HTTP/1.1 {} X\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        status,
+                        body.len(),
+                        body
+                    )
+                    .as_bytes(),
+                )
+                .unwrap();
+            requests.push((headers, String::from_utf8(req_body).unwrap()));
+        }
+        requests
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_message_builder() {
+        let msg = Message::new("Announcing Rust 1.80", Color::Orange)
+            .date("")
+            .markdown("content")
+            .button("origin link", "https://example.com/a")
+            .button("docs", "https://example.com/b");
+        assert_eq!(msg.date, None);
+        assert_eq!(msg.elements.len(), 2);
+        assert_eq!(
+            msg.elements[1],
+            Element::Buttons(vec![
+                Button {
+                    text: "origin link".to_string(),
+                    url: "https://example.com/a".to_string(),
+                },
+                Button {
+                    text: "docs".to_string(),
+                    url: "https://example.com/b".to_string(),
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_updated_message() {
+        let msg = Message::updated(
+            "Announcing Rust 1.80",
+            "2024-07-26",
+            "https://example.com/a",
+        );
+        assert_eq!(msg.title, "[Updated] Announcing Rust 1.80");
+        assert_eq!(msg.color, Color::Grey);
+        assert_eq!(
+            msg.elements[0],
+            Element::Markdown(
+                "**Announcing Rust 1.80** has been updated at 2024-07-26.".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_from_source() {
+        let redis = Redis::new("", "", "localhost", 6379).unwrap();
+        let source = ArticleSourceConfig::default();
+        assert!(from_source(&redis, "Rust Blog", &source, None).is_empty());
+        let source = ArticleSourceConfig {
+            webhooks: vec!["http://example.com/webhook1".into()],
+            slack_webhooks: vec!["https://hooks.slack.com/services/T000/B000/XXXX".to_string()],
+            wecom_webhooks: vec![
+                "https://qyapi.weixin.qq.com/cgi-bin/webhook/send?key=xxx".to_string()
+            ],
+            email_digest: true,
+            ..Default::default()
+        };
+        assert_eq!(from_source(&redis, "Rust Blog", &source, None).len(), 4);
+    }
+
+    /// Counts its deliveries in `count`, failing every one if `fail` is set.
+    struct CountingNotifier {
+        fail: bool,
+        count: Arc<AtomicUsize>,
+    }
+
+    impl Notifier for CountingNotifier {
+        fn notify<'a>(&'a self, _msg: &'a Message) -> NotifyFuture<'a> {
+            Box::pin(async move {
+                self.count.fetch_add(1, Ordering::SeqCst);
+                if self.fail {
+                    return Err(anyhow::anyhow!("unreachable webhook"));
+                }
+                Ok(())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_notify_all_isolates_failures() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let notifiers: Vec<Box<dyn Notifier>> = [true, false, true]
+            .into_iter()
+            .map(|fail| {
+                Box::new(CountingNotifier {
+                    fail,
+                    count: count.clone(),
+                }) as Box<dyn Notifier>
+            })
+            .collect();
+        let msg = Message::new("Announcing Rust 1.80", Color::Orange);
+        let err = notify_all(&notifiers, &msg).await.unwrap_err();
+        assert_eq!(count.load(Ordering::SeqCst), 3);
+        assert_eq!(
+            err.to_string(),
+            "2 of 3 notifiers failed to deliver Announcing Rust 1.80"
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use regex::Regex;
+use serde_json::{json, Value};
+use tracing::error;
+
+use crate::truncate_str;
+
+use super::{delivery_result, Element, Message, Notifier, NotifyFuture};
+
+/// The max number of characters of a header block.
+const MAX_HEADER_LEN: usize = 150;
+
+/// The max number of characters of the text of a section block.
+const MAX_SECTION_LEN: usize = 3000;
+
+/// Delivers messages as Block Kit blocks to Slack incoming webhooks.
+pub struct SlackNotifier {
+    client: reqwest::Client,
+    webhooks: Vec<String>,
+}
+
+impl SlackNotifier {
+    pub fn new(webhooks: Vec<String>) -> Self {
+        SlackNotifier {
+            client: reqwest::Client::new(),
+            webhooks,
+        }
+    }
+
+    async fn send(&self, webhook: &str, req: &Value) -> anyhow::Result<()> {
+        // the url holds the secret of the webhook, keep it out of the logs
+        let res = self
+            .client
+            .post(webhook)
+            .json(req)
+            .send()
+            .await
+            .map_err(reqwest::Error::without_url)?;
+        // incoming webhooks answer `ok`, or an error code such as `invalid_blocks`
+        let status = res.status();
+        if !status.is_success() {
+            return Err(anyhow::anyhow!(
+                "status: {}, msg: {}",
+                status,
+                res.text().await.unwrap_or_default()
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Notifier for SlackNotifier {
+    fn notify<'a>(&'a self, msg: &'a Message) -> NotifyFuture<'a> {
+        Box::pin(async move {
+            let req = &render(msg);
+            // a failing webhook does not keep the others from the message
+            let mut failed = 0;
+            for webhook in &self.webhooks {
+                if let Err(e) = self.send(webhook, req).await {
+                    error!("send {} to slack failed: {}", msg.title, e);
+                    failed += 1;
+                }
+            }
+            delivery_result(failed, self.webhooks.len(), "slack webhooks")
+        })
+    }
+}
+
+/// Converts the markdown of the channels to Slack mrkdwn.
+pub fn mrkdwn(s: &str) -> String {
+    let s = s
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    let link = Regex::new(r"\[([^\]]*)\]\(([^)\s]+)\)").unwrap();
+    let s = link.replace_all(&s, "<$2|$1>");
+    let bold = Regex::new(r"\*\*(.+?)\*\*").unwrap();
+    bold.replace_all(&s, "*$1*").into_owned()
+}
+
+/// Converts the text of a section, cut before the conversion so that the cut
+/// cannot split an entity or a link. Escaping lengthens the text, the cut is
+/// shortened until the mrkdwn fits.
+fn section_text(s: &str) -> String {
+    let mut max = MAX_SECTION_LEN - 3;
+    loop {
+        let res = mrkdwn(&truncate_str(s, max));
+        let len = res.chars().count();
+        if len <= MAX_SECTION_LEN {
+            return res;
+        }
+        max -= len - MAX_SECTION_LEN;
+    }
+}
+
+/// Renders a markdown element as sections, its `---` rules becoming dividers.
+fn markdown_blocks(content: &str) -> Vec<Value> {
+    let mut blocks = vec![];
+    let mut text = String::new();
+    for line in content.lines().chain(std::iter::once("---")) {
+        if line.trim() != "---" {
+            text.push_str(line);
+            text.push('\n');
+            continue;
+        }
+        if !text.trim().is_empty() {
+            blocks.push(json!({
+                "type": "section",
+                "text": {
+                    "type": "mrkdwn",
+                    "text": section_text(text.trim()),
+                }
+            }));
+        }
+        text.clear();
+        blocks.push(json!({ "type": "divider" }));
+    }
+    // the divider closing the content
+    blocks.pop();
+    blocks
+}
+
+pub fn render(msg: &Message) -> Value {
+    let mut blocks = vec![json!({
+        "type": "header",
+        "text": {
+            "type": "plain_text",
+            // `truncate_str` appends `...`
+            "text": truncate_str(&msg.title, MAX_HEADER_LEN - 3),
+        }
+    })];
+    if let Some(date) = &msg.date {
+        blocks.push(json!({
+            "type": "context",
+            "elements": [{
+                "type": "mrkdwn",
+                "text": mrkdwn(date),
+            }]
+        }));
+    }
+    for element in &msg.elements {
+        match element {
+            Element::Markdown(content) => blocks.extend(markdown_blocks(content)),
+            Element::Image { url, alt } => blocks.push(json!({
+                "type": "image",
+                "image_url": url,
+                "alt_text": alt,
+            })),
+            Element::Buttons(buttons) => blocks.push(json!({
+                "type": "actions",
+                "elements": buttons
+                    .iter()
+                    .map(|v| json!({
+                        "type": "button",
+                        "text": {
+                            "type": "plain_text",
+                            "text": v.text,
+                        },
+                        "url": v.url,
+                    }))
+                    .collect::<Vec<_>>(),
+            })),
+        }
+    }
+    json!({
+        // shown in notifications
+        "text": msg.title,
+        "blocks": blocks,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, thread};
+
+    use crate::notifier::{test_server::serve, Color};
+
+    use super::*;
+
+    #[test]
+    fn test_mrkdwn() {
+        assert_eq!(
+            mrkdwn("**Author**: [Go & Rust](https://example.com/a?b=1&c=2) <3"),
+            "*Author*: <https://example.com/a?b=1&amp;c=2|Go &amp; Rust> &lt;3"
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let msg = Message::new("Announcing Rust 1.80", Color::Orange)
+            .date("2024-07-25T00:00:00+00:00")
+            .markdown("summary\n---\n\n**以下内容为 OpenAI 生成，仅供参考：**\n\nabstract\n---\n")
+            .button("origin link", "https://example.com/a");
+        assert_eq!(
+            render(&msg),
+            json!({
+                "text": "Announcing Rust 1.80",
+                "blocks": [
+                    {
+                        "type": "header",
+                        "text": {
+                            "type": "plain_text",
+                            "text": "Announcing Rust 1.80",
+                        }
+                    },
+                    {
+                        "type": "context",
+                        "elements": [{
+                            "type": "mrkdwn",
+                            "text": "2024-07-25T00:00:00+00:00",
+                        }]
+                    },
+                    {
+                        "type": "section",
+                        "text": {
+                            "type": "mrkdwn",
+                            "text": "summary",
+                        }
+                    },
+                    { "type": "divider" },
+                    {
+                        "type": "section",
+                        "text": {
+                            "type": "mrkdwn",
+                            "text": "*以下内容为 OpenAI 生成，仅供参考：*\n\nabstract",
+                        }
+                    },
+                    { "type": "divider" },
+                    {
+                        "type": "actions",
+                        "elements": [{
+                            "type": "button",
+                            "text": {
+                                "type": "plain_text",
+                                "text": "origin link",
+                            },
+                            "url": "https://example.com/a",
+                        }]
+                    }
+                ]
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_notify_isolates_webhooks() {
+        // nothing listens on the port of a dropped listener
+        let unreachable = TcpListener::bind("127.0.0.1:0").unwrap();
+        let unreachable_port = unreachable.local_addr().unwrap().port();
+        drop(unreachable);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || serve(listener, vec![(200, "ok")]));
+
+        let notifier = SlackNotifier::new(vec![
+            format!("http://127.0.0.1:{}/services/a", unreachable_port),
+            format!("http://127.0.0.1:{}/services/b", port),
+        ]);
+        let msg = Message::new("Announcing Rust 1.80", Color::Orange).markdown("summary");
+        let err = notifier.notify(&msg).await.unwrap_err();
+        assert_eq!(err.to_string(), "1 of 2 slack webhooks failed");
+        assert_eq!(server.join().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_render_truncate() {
+        let msg = Message::new("t".repeat(200), Color::Orange).markdown("a & b ".repeat(1000));
+        let blocks = render(&msg)["blocks"].as_array().unwrap().clone();
+        let header = blocks[0]["text"]["text"].as_str().unwrap();
+        assert_eq!(header.chars().count(), MAX_HEADER_LEN);
+        let section = blocks[1]["text"]["text"].as_str().unwrap();
+        assert!(section.chars().count() <= MAX_SECTION_LEN);
+        assert!(section.ends_with("..."));
+        assert!(!section.contains("&am..."));
+    }
+}
@@ -0,0 +1,228 @@
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::error;
+
+use crate::{conf::TelegramChatConfig, truncate_str};
+
+use super::{delivery_result, Element, Message, Notifier, NotifyFuture};
+
+/// The max number of characters of the text of a message.
+const MAX_TEXT_LEN: usize = 4096;
+
+#[derive(Debug, Deserialize)]
+struct SendMessageResp {
+    ok: bool,
+    #[serde(default)]
+    description: String,
+}
+
+/// Delivers messages to Telegram chats with the `sendMessage` method of the Bot API.
+pub struct TelegramNotifier {
+    client: reqwest::Client,
+    chats: Vec<TelegramChatConfig>,
+}
+
+impl TelegramNotifier {
+    pub fn new(chats: Vec<TelegramChatConfig>) -> Self {
+        TelegramNotifier {
+            client: reqwest::Client::new(),
+            chats,
+        }
+    }
+
+    async fn send(&self, chat: &TelegramChatConfig, req: &Value) -> anyhow::Result<()> {
+        let url = format!("https://api.telegram.org/bot{}/sendMessage", chat.bot_token);
+        // the url holds the bot token, keep it out of the logs
+        let res = self
+            .client
+            .post(url)
+            .json(req)
+            .send()
+            .await
+            .map_err(reqwest::Error::without_url)?;
+        let status = res.status();
+        let body = res.text().await.map_err(reqwest::Error::without_url)?;
+        // failures are answered with a 4xx status and the same body
+        match serde_json::from_str::<SendMessageResp>(&body) {
+            Ok(v) if v.ok => Ok(()),
+            Ok(v) => Err(anyhow::anyhow!("{}", v.description)),
+            Err(_) => Err(anyhow::anyhow!("status: {}, msg: {}", status, body)),
+        }
+    }
+}
+
+impl Notifier for TelegramNotifier {
+    fn notify<'a>(&'a self, msg: &'a Message) -> NotifyFuture<'a> {
+        Box::pin(async move {
+            // a failing chat does not keep the others from the message
+            let mut failed = 0;
+            for chat in &self.chats {
+                if let Err(e) = self.send(chat, &render(msg, &chat.chat_id)).await {
+                    error!(
+                        "send {} to telegram chat {} failed: {}",
+                        msg.title, chat.chat_id, e
+                    );
+                    failed += 1;
+                }
+            }
+            delivery_result(failed, self.chats.len(), "telegram chats")
+        })
+    }
+}
+
+/// Escapes the characters reserved by MarkdownV2 outside of entities.
+pub fn escape(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    for c in s.chars() {
+        if "\\_*[]()~`>#+-=|{}.!".contains(c) {
+            res.push('\\');
+        }
+        res.push(c);
+    }
+    res
+}
+
+/// Converts the `[text](url)` links of `s` and escapes the rest.
+fn convert_links(s: &str) -> String {
+    let link = Regex::new(r"\[([^\]]*)\]\(([^)\s]+)\)").unwrap();
+    let mut res = String::with_capacity(s.len());
+    let mut last = 0;
+    for caps in link.captures_iter(s) {
+        let m = caps.get(0).unwrap();
+        res.push_str(&escape(&s[last..m.start()]));
+        // only `)` and `\` are escaped in the url of a link
+        let url = caps[2].replace('\\', "\\\\").replace(')', "\\)");
+        res.push_str(&format!("[{}]({})", escape(&caps[1]), url));
+        last = m.end();
+    }
+    res.push_str(&escape(&s[last..]));
+    res
+}
+
+/// Converts the markdown of the channels to MarkdownV2.
+pub fn markdown_v2(s: &str) -> String {
+    let bold = Regex::new(r"\*\*(.+?)\*\*").unwrap();
+    let mut res = String::with_capacity(s.len());
+    let mut last = 0;
+    for caps in bold.captures_iter(s) {
+        let m = caps.get(0).unwrap();
+        res.push_str(&convert_links(&s[last..m.start()]));
+        res.push_str(&format!("*{}*", convert_links(&caps[1])));
+        last = m.end();
+    }
+    res.push_str(&convert_links(&s[last..]));
+    res
+}
+
+/// Renders the message as the parameters of `sendMessage`, its buttons as an
+/// inline keyboard with a row per group of buttons. A message whose MarkdownV2
+/// is over the limit is sent as truncated plain text, as cutting MarkdownV2
+/// may leave an entity open.
+pub fn render(msg: &Message, chat_id: &str) -> Value {
+    let mut parts = vec![format!("*{}*", escape(&msg.title))];
+    let mut plain = vec![msg.title.clone()];
+    if let Some(date) = &msg.date {
+        parts.push(format!("_{}_", escape(date)));
+        plain.push(date.clone());
+    }
+    let mut keyboard = vec![];
+    for element in &msg.elements {
+        match element {
+            Element::Markdown(content) => {
+                parts.push(markdown_v2(content.trim()));
+                plain.push(content.trim().to_string());
+            }
+            Element::Image { url, alt } => {
+                parts.push(markdown_v2(&format!("[{}]({})", alt, url)));
+                plain.push(format!("{}: {}", alt, url));
+            }
+            Element::Buttons(buttons) => keyboard.push(
+                buttons
+                    .iter()
+                    .map(|v| json!({ "text": v.text, "url": v.url }))
+                    .collect::<Vec<_>>(),
+            ),
+        }
+    }
+    let text = parts.join("\n\n");
+    let mut req = if text.chars().count() <= MAX_TEXT_LEN {
+        json!({
+            "chat_id": chat_id,
+            "text": text,
+            "parse_mode": "MarkdownV2",
+            "link_preview_options": { "is_disabled": true },
+        })
+    } else {
+        json!({
+            "chat_id": chat_id,
+            // `truncate_str` appends `...`
+            "text": truncate_str(&plain.join("\n\n"), MAX_TEXT_LEN - 3),
+            "link_preview_options": { "is_disabled": true },
+        })
+    };
+    if !keyboard.is_empty() {
+        req["reply_markup"] = json!({ "inline_keyboard": keyboard });
+    }
+    req
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::notifier::Color;
+
+    use super::*;
+
+    #[test]
+    fn test_markdown_v2() {
+        assert_eq!(
+            markdown_v2("**Author**: Go-Team. See [Go 1.23 (rc)](https://go.dev/a_b) !"),
+            "*Author*: Go\\-Team\\. See [Go 1\\.23 \\(rc\\)](https://go.dev/a_b) \\!"
+        );
+        assert_eq!(
+            markdown_v2("**[tokio](https://tokio.rs)** 1.0"),
+            "*[tokio](https://tokio.rs)* 1\\.0"
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let msg = Message::new("Announcing Rust 1.80", Color::Orange)
+            .date("2024-07-25")
+            .markdown("summary.\n")
+            .button("origin link", "https://example.com/a")
+            .button("docs", "https://example.com/b");
+        assert_eq!(
+            render(&msg, "@hedon"),
+            json!({
+                "chat_id": "@hedon",
+                "text": "*Announcing Rust 1\\.80*\n\n_2024\\-07\\-25_\n\nsummary\\.",
+                "parse_mode": "MarkdownV2",
+                "link_preview_options": { "is_disabled": true },
+                "reply_markup": {
+                    "inline_keyboard": [[
+                        { "text": "origin link", "url": "https://example.com/a" },
+                        { "text": "docs", "url": "https://example.com/b" },
+                    ]]
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_render_too_long() {
+        // escaping doubles the length of the content
+        let content = format!("[link](https://example.com/a) {}", ".".repeat(3000));
+        let msg = Message::new("Go 1.23", Color::Blue).markdown(content.as_str());
+        let req = render(&msg, "@hedon");
+        assert!(req.get("parse_mode").is_none());
+        let text = req["text"].as_str().unwrap();
+        assert_eq!(text.chars().count(), 3039);
+        assert!(text.starts_with("Go 1.23\n\n[link](https://example.com/a) ..."));
+
+        let msg = Message::new("Go 1.23", Color::Blue).markdown(".".repeat(5000).as_str());
+        let text = render(&msg, "@hedon")["text"].as_str().unwrap().to_string();
+        assert_eq!(text.chars().count(), MAX_TEXT_LEN);
+        assert!(text.ends_with("..."));
+    }
+}
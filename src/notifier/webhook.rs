@@ -0,0 +1,272 @@
+use std::time::Duration;
+
+use handlebars::{handlebars_helper, Handlebars};
+use serde::Serialize;
+use serde_json::Value;
+use tracing::{error, warn};
+
+use crate::{canonical::host_of, conf::HttpWebhookConfig};
+
+/// The max number of retries of a request, the last one waits 16 seconds.
+const MAX_RETRIES: u32 = 5;
+
+use super::{delivery_result, Button, Element, Message, Notifier, NotifyFuture};
+
+/// The fields of a message available to the templates.
+#[derive(Debug, Serialize)]
+pub struct TemplateData<'a> {
+    pub channel: &'a str,
+    pub title: &'a str,
+    pub date: Option<&'a str>,
+    // the name of the Feishu template, e.g. `orange`
+    pub color: &'static str,
+    // the markdown elements, separated by blank lines
+    pub content: String,
+    // the url of the first button, usually the article
+    pub url: Option<&'a str>,
+    pub buttons: Vec<&'a Button>,
+    pub images: Vec<&'a str>,
+}
+
+impl<'a> TemplateData<'a> {
+    pub fn new(channel: &'a str, msg: &'a Message) -> Self {
+        let mut content = vec![];
+        let mut buttons = vec![];
+        let mut images = vec![];
+        for element in &msg.elements {
+            match element {
+                Element::Markdown(v) => content.push(v.trim()),
+                Element::Image { url, .. } => images.push(url.as_str()),
+                Element::Buttons(v) => buttons.extend(v),
+            }
+        }
+        TemplateData {
+            channel,
+            title: &msg.title,
+            date: msg.date.as_deref(),
+            color: super::feishu::template(msg.color).as_str(),
+            content: content.join("\n\n"),
+            url: buttons.first().map(|v| v.url.as_str()),
+            buttons,
+            images,
+        }
+    }
+}
+
+handlebars_helper!(json: |v: Json| v.to_string());
+
+/// Values are escaped for JSON strings, so `"{{title}}"` is always valid.
+/// `{{{json buttons}}}` writes a field as a JSON value.
+fn registry() -> Handlebars<'static> {
+    let mut hb = Handlebars::new();
+    hb.register_escape_fn(|s| {
+        let quoted = Value::from(s).to_string();
+        quoted[1..quoted.len() - 1].to_string()
+    });
+    hb.register_helper("json", Box::new(json));
+    hb
+}
+
+/// Renders the body of a request, which must be valid JSON.
+pub fn render(template: &str, data: &TemplateData) -> anyhow::Result<Value> {
+    let body = registry().render_template(template, data)?;
+    serde_json::from_str(&body)
+        .map_err(|e| anyhow::anyhow!("the template renders invalid json: {}, body: {}", e, body))
+}
+
+/// Posts messages rendered by a template to arbitrary HTTP endpoints.
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    channel: String,
+    webhooks: Vec<HttpWebhookConfig>,
+}
+
+impl WebhookNotifier {
+    pub fn new(channel: &str, webhooks: Vec<HttpWebhookConfig>) -> Self {
+        WebhookNotifier {
+            client: reqwest::Client::new(),
+            channel: channel.to_string(),
+            webhooks,
+        }
+    }
+
+    fn is_expected(webhook: &HttpWebhookConfig, status: u16) -> bool {
+        if webhook.expected_status.is_empty() {
+            return (200..300).contains(&status);
+        }
+        webhook.expected_status.contains(&status)
+    }
+
+    /// Posts the body, retrying with an exponential backoff. Returns the
+    /// last unexpected response if every attempt got one.
+    async fn post(
+        &self,
+        webhook: &HttpWebhookConfig,
+        name: &str,
+        body: &Value,
+    ) -> anyhow::Result<Option<(u16, String)>> {
+        let retries = webhook.retries.min(MAX_RETRIES);
+        let mut attempt = 0;
+        loop {
+            let mut req = self.client.post(&webhook.url).json(body);
+            for (k, v) in &webhook.headers {
+                req = req.header(k, v);
+            }
+            // the url usually holds a token, keep it out of the logs
+            let res = match req.send().await.map_err(reqwest::Error::without_url) {
+                Ok(res) => {
+                    let status = res.status().as_u16();
+                    if Self::is_expected(webhook, status) {
+                        return Ok(None);
+                    }
+                    Ok((status, res.text().await.unwrap_or_default()))
+                }
+                Err(e) => Err(e),
+            };
+            if attempt >= retries {
+                return Ok(Some(res?));
+            }
+            match &res {
+                Ok((status, text)) => {
+                    warn!("post to {} got status {}: {}, retrying", name, status, text)
+                }
+                Err(e) => warn!("post to {} failed: {}, retrying", name, e),
+            }
+            tokio::time::sleep(Duration::from_secs(1 << attempt)).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// Names a webhook in the logs by its position and host, not its url.
+fn display_name(index: usize, url: &str) -> String {
+    format!(
+        "http webhook #{} ({})",
+        index + 1,
+        host_of(url).unwrap_or_default()
+    )
+}
+
+impl Notifier for WebhookNotifier {
+    fn notify<'a>(&'a self, msg: &'a Message) -> NotifyFuture<'a> {
+        Box::pin(async move {
+            let data = TemplateData::new(&self.channel, msg);
+            // a failing webhook does not keep the others from the message
+            let mut failed = 0;
+            for (i, webhook) in self.webhooks.iter().enumerate() {
+                let name = display_name(i, &webhook.url);
+                let res = match render(&webhook.template, &data) {
+                    Ok(body) => self.post(webhook, &name, &body).await,
+                    Err(e) => Err(e),
+                };
+                match res {
+                    Ok(None) => continue,
+                    Ok(Some((status, text))) => error!(
+                        "send {} to {} failed, status: {}, msg: {}",
+                        msg.title, name, status, text
+                    ),
+                    Err(e) => error!("send {} to {} failed: {}", msg.title, name, e),
+                }
+                failed += 1;
+            }
+            delivery_result(failed, self.webhooks.len(), "http webhooks")
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, net::TcpListener, thread};
+
+    use serde_json::json;
+
+    use crate::notifier::{test_server::serve, Color};
+
+    use super::*;
+
+    fn message() -> Message {
+        Message::new("Announcing \"Rust\" 1.80", Color::Orange)
+            .date("2024-07-25")
+            .markdown("**Author**: The Rust Release Team\n")
+            .markdown("summary")
+            .button("origin link", "https://blog.rust-lang.org/a")
+    }
+
+    #[test]
+    fn test_render() {
+        let msg = message();
+        let data = TemplateData::new("Rust Blog", &msg);
+        let template = r#"{
+            "source": "{{channel}}",
+            "text": "{{title}} ({{date}})\n{{content}}",
+            "link": "{{url}}",
+            "color": "{{color}}",
+            "links": {{{json buttons}}}
+        }"#;
+        assert_eq!(
+            render(template, &data).unwrap(),
+            json!({
+                "source": "Rust Blog",
+                "text": "Announcing \"Rust\" 1.80 (2024-07-25)\n**Author**: The Rust Release Team\n\nsummary",
+                "link": "https://blog.rust-lang.org/a",
+                "color": "orange",
+                "links": [{ "text": "origin link", "url": "https://blog.rust-lang.org/a" }],
+            })
+        );
+        assert!(render(r#"{"text": {{title}}}"#, &data).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_notify_retry() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || serve(listener, vec![(503, ""), (202, "")]));
+
+        let notifier = WebhookNotifier::new(
+            "Rust Blog",
+            vec![HttpWebhookConfig {
+                url: format!("http://127.0.0.1:{}/hooks", port),
+                template: r#"{"title": "{{title}}"}"#.to_string(),
+                headers: BTreeMap::from([("X-Token".to_string(), "secret".to_string())]),
+                expected_status: vec![202],
+                retries: 1,
+            }],
+        );
+        notifier.notify(&message()).await.unwrap();
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].0.contains(&"x-token: secret".to_string()));
+        assert_eq!(requests[1].1, r#"{"title":"Announcing \"Rust\" 1.80"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_notify_isolates_webhooks() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || serve(listener, vec![(200, "")]));
+
+        let webhook = |template: &str| HttpWebhookConfig {
+            url: format!("http://127.0.0.1:{}/hooks?token=secret", port),
+            template: template.to_string(),
+            headers: BTreeMap::new(),
+            expected_status: vec![],
+            retries: 0,
+        };
+        // the first template renders invalid json
+        let notifier = WebhookNotifier::new(
+            "Rust Blog",
+            vec![
+                webhook(r#"{"title": {{title}}}"#),
+                webhook(r#"{"title": "{{title}}"}"#),
+            ],
+        );
+        let err = notifier.notify(&message()).await.unwrap_err();
+        assert_eq!(err.to_string(), "1 of 2 http webhooks failed");
+        assert_eq!(server.join().unwrap().len(), 1);
+        assert_eq!(
+            display_name(0, &notifier.webhooks[0].url),
+            "http webhook #1 (127.0.0.1)"
+        );
+    }
+}
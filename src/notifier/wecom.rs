@@ -0,0 +1,153 @@
+use serde_json::{json, Value};
+use tracing::error;
+
+use super::{delivery_result, dingtalk::post, Color, Element, Message, Notifier, NotifyFuture};
+
+/// The max number of bytes of the content of a markdown message.
+const MAX_CONTENT_BYTES: usize = 4096;
+
+/// Delivers messages as markdown messages to WeCom (企业微信) robot webhooks.
+pub struct WeComNotifier {
+    client: reqwest::Client,
+    webhooks: Vec<String>,
+}
+
+impl WeComNotifier {
+    pub fn new(webhooks: Vec<String>) -> Self {
+        WeComNotifier {
+            client: reqwest::Client::new(),
+            webhooks,
+        }
+    }
+}
+
+impl Notifier for WeComNotifier {
+    fn notify<'a>(&'a self, msg: &'a Message) -> NotifyFuture<'a> {
+        Box::pin(async move {
+            let req = &render(msg);
+            // a failing robot does not keep the others from the message
+            let mut failed = 0;
+            for webhook in &self.webhooks {
+                if let Err(e) = post(&self.client, webhook, req).await {
+                    error!("send {} to wecom failed: {}", msg.title, e);
+                    failed += 1;
+                }
+            }
+            delivery_result(failed, self.webhooks.len(), "wecom robots")
+        })
+    }
+}
+
+/// The closest of the three font colors of WeCom markdown, if any.
+fn font_color(color: Color) -> Option<&'static str> {
+    match color {
+        Color::Red | Color::Carmine | Color::Orange => Some("warning"),
+        Color::Green | Color::Turquoise => Some("info"),
+        Color::Grey => Some("comment"),
+        _ => None,
+    }
+}
+
+/// Truncates `s` to at most `max` bytes on a char boundary.
+fn truncate_bytes(s: &str, max: usize) -> &str {
+    if s.len() <= max {
+        return s;
+    }
+    let mut end = max;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// Renders the message as a markdown message. WeCom markdown has neither
+/// images nor buttons, they are rendered as links.
+pub fn render(msg: &Message) -> Value {
+    let title = match font_color(msg.color) {
+        Some(color) => format!("### <font color=\"{}\">{}</font>", color, msg.title),
+        None => format!("### {}", msg.title),
+    };
+    let mut parts = vec![title];
+    if let Some(date) = &msg.date {
+        parts.push(format!("> <font color=\"comment\">{}</font>", date));
+    }
+    for element in &msg.elements {
+        match element {
+            Element::Markdown(content) => parts.push(content.trim().to_string()),
+            Element::Image { url, alt } => parts.push(format!("[{}]({})", alt, url)),
+            Element::Buttons(buttons) => parts.push(
+                buttons
+                    .iter()
+                    .map(|v| format!("[{}]({})", v.text, v.url))
+                    .collect::<Vec<_>>()
+                    .join(" | "),
+            ),
+        }
+    }
+    let content = parts.join("\n");
+    json!({
+        "msgtype": "markdown",
+        "markdown": {
+            "content": truncate_bytes(&content, MAX_CONTENT_BYTES),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, thread};
+
+    use crate::notifier::test_server::serve;
+
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let msg = Message::new("Go 1.22.5 (security)", Color::Red)
+            .date("2024-07-02")
+            .markdown("**Summary**: security fixes to net/http\n")
+            .button("release notes", "https://go.dev/doc/devel/release#go1.22.5")
+            .button("download", "https://go.dev/dl/#go1.22.5");
+        assert_eq!(
+            render(&msg),
+            json!({
+                "msgtype": "markdown",
+                "markdown": {
+                    "content": "### <font color=\"warning\">Go 1.22.5 (security)</font>\n> <font color=\"comment\">2024-07-02</font>\n**Summary**: security fixes to net/http\n[release notes](https://go.dev/doc/devel/release#go1.22.5) | [download](https://go.dev/dl/#go1.22.5)",
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_truncate_bytes() {
+        assert_eq!(truncate_bytes("abc", 4), "abc");
+        // "以" is 3 bytes long
+        assert_eq!(truncate_bytes("a以下", 5), "a以");
+        assert_eq!(truncate_bytes("a以下", 3), "a");
+    }
+
+    #[tokio::test]
+    async fn test_notify_isolates_webhooks() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            serve(
+                listener,
+                vec![
+                    (502, "<html>Bad Gateway</html>"),
+                    (200, r#"{"errcode":0,"errmsg":"ok"}"#),
+                ],
+            )
+        });
+
+        let notifier = WeComNotifier::new(vec![
+            format!("http://127.0.0.1:{}/cgi-bin/webhook/send?key=a", port),
+            format!("http://127.0.0.1:{}/cgi-bin/webhook/send?key=b", port),
+        ]);
+        let msg = Message::new("Go 1.22.5", Color::Blue).markdown("content");
+        let err = notifier.notify(&msg).await.unwrap_err();
+        assert_eq!(err.to_string(), "1 of 2 wecom robots failed");
+        assert_eq!(server.join().unwrap().len(), 2);
+    }
+}
@@ -1,15 +1,43 @@
 use redis::{Client, Commands, RedisError, RedisResult};
+use sha2::{Digest, Sha256};
+use tracing::info;
 
+use crate::{canonical::normalize_url, conf::ArticleSourceConfig};
+
+#[derive(Clone)]
 pub struct Redis {
     pub client: Client,
 }
 
+/// What the store knows about an item when it is observed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemState {
+    // never seen before.
+    New,
+    // seen before, but its `updated` value or content changed since.
+    Updated,
+    // seen before and nothing changed.
+    Unchanged,
+}
+
 impl Redis {
     pub const HSET_GO_WEEKLY_KEY: &'static str = "hedon-bot:go-weekly-memory";
     pub const HSET_REDIS_BLOG_KEY: &'static str = "hedon-bot:redis-blog-memory";
     pub const HSET_GO_BLOG_KEY: &'static str = "hedon-bot:go-blog-memory";
     pub const HSET_RUST_BLOG_KEY: &'static str = "hedon-bot:rust-blog-memory";
     pub const HSET_RUST_INSIDE_BLOG_KEY: &'static str = "hedon-bot:rust-inside-blog-memory";
+    pub const HSET_THIS_WEEK_IN_RUST_KEY: &'static str = "hedon-bot:this-week-in-rust-memory";
+    pub const HSET_CRATES_IO_KEY: &'static str = "hedon-bot:crates-io-memory";
+    pub const HSET_GO_RELEASES_KEY: &'static str = "hedon-bot:go-releases-memory";
+    pub const HSET_RUSTSEC_KEY: &'static str = "hedon-bot:rustsec-memory";
+    pub const HSET_GO_VULN_KEY: &'static str = "hedon-bot:go-vuln-memory";
+    pub const HSET_GITHUB_RELEASES_KEY: &'static str = "hedon-bot:github-releases-memory";
+    pub const HSET_NEWSLETTER_KEY_PREFIX: &'static str = "hedon-bot:newsletter-memory";
+    pub const HSET_MAILBOX_KEY_PREFIX: &'static str = "hedon-bot:mailbox-memory";
+    pub const HSET_MEDIA_KEY_PREFIX: &'static str = "hedon-bot:media-memory";
+    pub const HSET_SCRAPE_KEY_PREFIX: &'static str = "hedon-bot:scrape-memory";
+    pub const HSET_DEDUP_GROUP_KEY_PREFIX: &'static str = "hedon-bot:dedup-group-memory";
+    pub const LIST_EMAIL_DIGEST_KEY: &'static str = "hedon-bot:email-digest";
 
     pub fn new(username: &str, password: &str, host: &str, port: u32) -> anyhow::Result<Redis> {
         let client = connect_redis(username, password, host, port)?;
@@ -26,6 +54,99 @@ impl Redis {
         res.unwrap_or(true)
     }
 
+    pub fn hset(&self, key: &str, field: &str, value: &str) {
+        let conn = self.client.get_connection();
+        if conn.is_err() {
+            return;
+        }
+        let mut conn = conn.unwrap();
+        let _res: Result<i8, RedisError> = conn.hset(key, field, value);
+    }
+
+    /// Marks the canonical `url` as pushed in the dedup `group`, returns
+    /// `false` if another channel of the group has pushed it already.
+    pub fn setnx_group(&self, group: &str, url: &str) -> bool {
+        self.setnx(&Self::group_key(group), url)
+    }
+
+    pub fn group_key(group: &str) -> String {
+        format!("{}:{}", Self::HSET_DEDUP_GROUP_KEY_PREFIX, group)
+    }
+
+    pub fn mailbox_key(name: &str) -> String {
+        format!(
+            "{}:{}",
+            Self::HSET_MAILBOX_KEY_PREFIX,
+            name.to_lowercase().replace(' ', "-")
+        )
+    }
+
+    pub fn media_key(name: &str) -> String {
+        format!(
+            "{}:{}",
+            Self::HSET_MEDIA_KEY_PREFIX,
+            name.to_lowercase().replace(' ', "-")
+        )
+    }
+
+    pub fn scrape_key(name: &str) -> String {
+        format!(
+            "{}:{}",
+            Self::HSET_SCRAPE_KEY_PREFIX,
+            name.to_lowercase().replace(' ', "-")
+        )
+    }
+
+    /// Records the `updated` value and content hash of `id` in the hash `key`
+    /// and reports how it compares with what was stored before.
+    ///
+    /// Items recorded by [`Redis::setnx`] only carry a marker, so they are
+    /// reported as unchanged the first time and get a fingerprint from then on.
+    pub fn observe(&self, key: &str, id: &str, updated: &str, content: &str) -> ItemState {
+        let conn = self.client.get_connection();
+        if conn.is_err() {
+            return ItemState::New;
+        }
+        let mut conn = conn.unwrap();
+        let fingerprint = fingerprint(updated, content);
+        let old: Result<Option<String>, RedisError> = conn.hget(key, id);
+        let state = match old {
+            Ok(None) => ItemState::New,
+            Ok(Some(v)) if v == fingerprint || v == "1" => ItemState::Unchanged,
+            Ok(Some(_)) => ItemState::Updated,
+            Err(_) => return ItemState::New,
+        };
+        let _res: Result<i8, RedisError> = conn.hset(key, id, &fingerprint);
+        state
+    }
+
+    /// Observes the item in the hash `key` and decides whether to push it,
+    /// following the policy of the channel's `source`: updated items only if
+    /// `notify_updates` is set, new ones unless another channel of the
+    /// `dedup_group` has pushed the same `link` already.
+    pub fn admit(
+        &self,
+        key: &str,
+        id: &str,
+        updated: &str,
+        content: &str,
+        link: &str,
+        source: &ArticleSourceConfig,
+    ) -> Option<ItemState> {
+        match self.observe(key, id, updated, content) {
+            ItemState::New => {}
+            ItemState::Updated if source.notify_updates => return Some(ItemState::Updated),
+            _ => return None,
+        }
+        if let Some(group) = &source.dedup_group {
+            if !self.setnx_group(group, &normalize_url(link)) {
+                info!("skip {}, it has been pushed in dedup group {}", link, group);
+                return None;
+            }
+        }
+        Some(ItemState::New)
+    }
+
     pub fn delete(&self, key: &str, url: &str) {
         let conn = self.client.get_connection();
         if conn.is_err() {
@@ -35,9 +156,38 @@ impl Redis {
         let _res: Result<i8, RedisError> = conn.hdel(key, url);
     }
 
+    /// Appends `value` to the list `key`. Unlike the markers, a lost value
+    /// is not pushed again, so failures are reported.
+    pub fn rpush(&self, key: &str, value: &str) -> anyhow::Result<()> {
+        let mut conn = self.client.get_connection()?;
+        let _: i64 = conn.rpush(key, value)?;
+        Ok(())
+    }
+
+    pub fn lrange_all(&self, key: &str) -> anyhow::Result<Vec<String>> {
+        let mut conn = self.client.get_connection()?;
+        Ok(conn.lrange(key, 0, -1)?)
+    }
+
+    /// Removes the first `n` values of the list `key`, keeping the ones
+    /// appended since they were read.
+    pub fn ltrim_front(&self, key: &str, n: usize) -> anyhow::Result<()> {
+        let mut conn = self.client.get_connection()?;
+        let _: () = conn.ltrim(key, n as isize, -1)?;
+        Ok(())
+    }
+
     // TODO: clear the post marker three months ago.
 }
 
+fn fingerprint(updated: &str, content: &str) -> String {
+    format!(
+        "{}|{:x}",
+        updated.trim(),
+        Sha256::digest(content.as_bytes())
+    )
+}
+
 fn connect_redis(username: &str, password: &str, host: &str, mut port: u32) -> RedisResult<Client> {
     if port == 0 {
         port = 6379;
@@ -51,7 +201,9 @@ mod tests {
 
     use redis::ConnectionLike;
 
-    use super::{connect_redis, Redis};
+    use crate::conf::ArticleSourceConfig;
+
+    use super::{connect_redis, fingerprint, ItemState, Redis};
 
     #[test]
     fn test_connect_redis() {
@@ -90,4 +242,104 @@ mod tests {
         assert!(redis.setnx(Redis::HSET_GO_WEEKLY_KEY, "go_weekly_url1"));
         assert!(!redis.setnx(Redis::HSET_GO_WEEKLY_KEY, "go_weekly_url1"));
     }
+
+    #[test]
+    fn test_group_key() {
+        assert_eq!(
+            Redis::group_key("go"),
+            "hedon-bot:dedup-group-memory:go".to_string()
+        );
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let a = fingerprint("2024-07-29T00:00:00+00:00", "content");
+        assert!(a.starts_with("2024-07-29T00:00:00+00:00|"));
+        assert_eq!(a, fingerprint(" 2024-07-29T00:00:00+00:00 ", "content"));
+        assert_ne!(a, fingerprint("2024-07-29T00:00:00+00:00", "content v2"));
+        assert_ne!(a, fingerprint("2024-07-30T00:00:00+00:00", "content"));
+    }
+
+    #[test]
+    fn test_observe() {
+        let redis = Redis::new("", "", "localhost", 6379);
+        if redis.is_err() {
+            println!("connect redis error");
+            return;
+        }
+        let redis = redis.unwrap();
+        if !redis.client.is_open() {
+            println!("connect redis error");
+            return;
+        }
+        let key = Redis::HSET_RUST_BLOG_KEY;
+        redis.delete(key, "rust_blog_url1");
+        assert_eq!(
+            redis.observe(key, "rust_blog_url1", "t1", "c1"),
+            ItemState::New
+        );
+        assert_eq!(
+            redis.observe(key, "rust_blog_url1", "t1", "c1"),
+            ItemState::Unchanged
+        );
+        assert_eq!(
+            redis.observe(key, "rust_blog_url1", "t2", "c1"),
+            ItemState::Updated
+        );
+        assert_eq!(
+            redis.observe(key, "rust_blog_url1", "t2", "c2"),
+            ItemState::Updated
+        );
+        redis.delete(key, "rust_blog_url1");
+
+        assert!(redis.setnx(key, "rust_blog_url1"));
+        assert_eq!(
+            redis.observe(key, "rust_blog_url1", "t1", "c1"),
+            ItemState::Unchanged
+        );
+        redis.delete(key, "rust_blog_url1");
+    }
+
+    #[test]
+    fn test_admit() {
+        let redis = Redis::new("", "", "localhost", 6379);
+        if redis.is_err() {
+            println!("connect redis error");
+            return;
+        }
+        let redis = redis.unwrap();
+        if !redis.client.is_open() {
+            println!("connect redis error");
+            return;
+        }
+        let (go, rust) = (Redis::HSET_GO_BLOG_KEY, Redis::HSET_RUST_BLOG_KEY);
+        let link = "https://example.com/admit?utm_source=a";
+        redis.delete(go, "admit1");
+        redis.delete(rust, "admit1");
+        redis.delete(&Redis::group_key("admit"), "https://example.com/admit");
+        let source = ArticleSourceConfig {
+            dedup_group: Some("admit".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            redis.admit(go, "admit1", "t1", "c1", link, &source),
+            Some(ItemState::New)
+        );
+        assert_eq!(redis.admit(go, "admit1", "t1", "c1", link, &source), None);
+        // updates are only pushed with `notify_updates`
+        assert_eq!(redis.admit(go, "admit1", "t2", "c1", link, &source), None);
+        let notify_updates = ArticleSourceConfig {
+            notify_updates: true,
+            ..source.clone()
+        };
+        assert_eq!(
+            redis.admit(go, "admit1", "t3", "c1", link, &notify_updates),
+            Some(ItemState::Updated)
+        );
+        // another channel of the group has pushed the link
+        assert_eq!(redis.admit(rust, "admit1", "t1", "c1", link, &source), None);
+        redis.delete(go, "admit1");
+        redis.delete(rust, "admit1");
+        redis.delete(&Redis::group_key("admit"), "https://example.com/admit");
+    }
 }